@@ -0,0 +1,72 @@
+use std::path::PathBuf;
+
+use anyhow::{Result, bail};
+
+const USAGE: &str = "usage: bard publish [--dry-run] [--base-url <url>] [<file or directory>...]
+       bard tidal login";
+
+/// 子命令
+#[derive(Debug)]
+pub enum Command {
+    /// 把本地歌词发布到 LRCLIB
+    Publish(PublishArgs),
+    /// 登录 Tidal，保存令牌用于获取歌词
+    TidalLogin,
+}
+
+#[derive(Debug, Default)]
+pub struct PublishArgs {
+    /// `--dry-run`：只打印要发布的内容
+    pub dry_run: bool,
+    /// `--base-url`：覆盖 `lyrics.lrclib.base_url`
+    pub base_url: Option<String>,
+    /// 音频文件或目录，为空时发布当前播放的曲目
+    pub paths: Vec<PathBuf>,
+}
+
+impl Command {
+    pub fn parse() -> Result<Self> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut args = args.into_iter();
+        match args.next().as_deref() {
+            Some("publish") => PublishArgs::parse_from(args).map(Command::Publish),
+            Some("tidal") => match args.next().as_deref() {
+                Some("login") => Ok(Command::TidalLogin),
+                _ => bail!("{}", USAGE),
+            },
+            Some("--help" | "-h") => {
+                println!("{}", USAGE);
+                std::process::exit(0);
+            }
+            Some(command) => bail!("unknown command `{}`\n{}", command, USAGE),
+            None => bail!("{}", USAGE),
+        }
+    }
+}
+
+impl PublishArgs {
+    fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = PublishArgs::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.split_once('=') {
+                Some(("--base-url", value)) => parsed.base_url = Some(value.to_string()),
+                _ if arg == "--base-url" => match args.next() {
+                    Some(value) => parsed.base_url = Some(value),
+                    None => bail!("--base-url requires a value\n{}", USAGE),
+                },
+                _ if arg == "--dry-run" || arg == "-n" => parsed.dry_run = true,
+                _ if arg == "--help" || arg == "-h" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ if arg.starts_with('-') => bail!("unknown argument `{}`\n{}", arg, USAGE),
+                _ => parsed.paths.push(PathBuf::from(arg)),
+            }
+        }
+        Ok(parsed)
+    }
+}
@@ -0,0 +1,20 @@
+use anyhow::Result;
+use shared::config::Config;
+
+use crate::cli::Command;
+
+mod cli;
+mod publish;
+mod tidal;
+
+fn main() -> Result<()> {
+    let command = Command::parse()?;
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("bard: {:#}, using defaults", e);
+        Config::default()
+    });
+    match command {
+        Command::Publish(args) => publish::run(args, &config),
+        Command::TidalLogin => tidal::login(&config),
+    }
+}
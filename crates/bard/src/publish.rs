@@ -0,0 +1,327 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result, bail};
+use lofty::file::{AudioFile, TaggedFileExt};
+use lofty::tag::Accessor;
+use shared::Error;
+use shared::config::{Config, ProviderKind};
+use shared::lyrics::LyricsPipeline;
+use shared::lyrics::lrclib::{LrclibProvider, PublishRequest};
+use shared::models::{SongInfo, SongStatus};
+use shared::player::sources_from_config;
+use shared::uri::resolve_local_path;
+
+use crate::cli::PublishArgs;
+
+/// 目录中会被发布的音频文件
+const AUDIO_EXTENSIONS: [&str; 9] = [
+    "mp3", "flac", "ogg", "opus", "m4a", "aac", "wav", "wv", "ape",
+];
+
+/// 各曲目的处理结果
+#[derive(Debug, Default, PartialEq)]
+struct Summary {
+    published: usize,
+    skipped: usize,
+    failed: usize,
+}
+
+/// 把本地歌词发布到 LRCLIB，有曲目失败时返回错误
+pub fn run(args: PublishArgs, config: &Config) -> Result<()> {
+    let summary = publish(&args, config)?;
+    if !args.dry_run {
+        println!(
+            "{} published, {} skipped, {} failed",
+            summary.published, summary.skipped, summary.failed
+        );
+    }
+    if summary.failed > 0 {
+        bail!("{} track(s) failed", summary.failed);
+    }
+    Ok(())
+}
+
+/// 歌词只从内嵌标签和外置文件读取，LRCLIB 上已有同等或更好的歌词时跳过
+fn publish(args: &PublishArgs, config: &Config) -> Result<Summary> {
+    let base_url = args
+        .base_url
+        .as_deref()
+        .unwrap_or(&config.lyrics.lrclib.base_url);
+    let lrclib = LrclibProvider::new(base_url);
+
+    let mut local = config.lyrics.clone();
+    local
+        .providers
+        .retain(|kind| matches!(kind, ProviderKind::Embedded | ProviderKind::Sidecar));
+    let pipeline = LyricsPipeline::from_config(&local);
+
+    let mut files = Vec::new();
+    if args.paths.is_empty() {
+        files.push(current_track(config)?);
+    }
+    for path in &args.paths {
+        if path.is_dir() {
+            collect_audio_files(path, &mut files)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+        } else {
+            let song = song_from_file(path)?;
+            files.push((song, path.clone()));
+        }
+    }
+
+    let mut summary = Summary::default();
+    for (song, path) in files {
+        let name = format!("{} - {}", song.artist, song.title);
+        let lyrics = match pipeline.fetch_local(&song, &path) {
+            Ok(lyrics) => lyrics,
+            Err(Error::NoLyrics(_)) => {
+                println!("skipped {}: no local lyrics", path.display());
+                summary.skipped += 1;
+                continue;
+            }
+            Err(e) => {
+                eprintln!("bard: {}: {}", path.display(), e);
+                summary.failed += 1;
+                continue;
+            }
+        };
+        let Some(request) = PublishRequest::new(&song, &lyrics) else {
+            println!(
+                "skipped {}: missing title, artist or duration",
+                path.display()
+            );
+            summary.skipped += 1;
+            continue;
+        };
+        if args.dry_run {
+            println!("{}", serde_json::to_string_pretty(&request)?);
+            continue;
+        }
+
+        match lrclib.existing(&request) {
+            Ok(Some(existing)) if existing.is_synced() || !lyrics.is_synced() => {
+                println!("skipped {}: already on LRCLIB", name);
+                summary.skipped += 1;
+                continue;
+            }
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("bard: {}: {}", name, e);
+                summary.failed += 1;
+                continue;
+            }
+        }
+        match lrclib.publish(&request) {
+            Ok(()) => {
+                println!("published {}", name);
+                summary.published += 1;
+            }
+            Err(e) => {
+                eprintln!("bard: {}: {}", name, e);
+                summary.failed += 1;
+            }
+        }
+    }
+
+    Ok(summary)
+}
+
+/// 当前播放的本地曲目，优先正在播放的播放器
+fn current_track(config: &Config) -> Result<(SongInfo, PathBuf)> {
+    let mut songs: Vec<SongInfo> = sources_from_config(&config.player)
+        .into_iter()
+        .flat_map(|mut source| source.snapshot().players)
+        .filter(|song| song.status != SongStatus::NoTrack)
+        .collect();
+    songs.sort_by_key(|song| song.status != SongStatus::Playing);
+    let song = songs.into_iter().next().context("No track is playing")?;
+    let url = song
+        .url
+        .as_deref()
+        .context("The current track has no URL")?;
+    let path = resolve_local_path(url)?;
+    Ok((song, path))
+}
+
+/// 递归收集目录中的音频文件，按路径排序
+fn collect_audio_files(dir: &Path, files: &mut Vec<(SongInfo, PathBuf)>) -> Result<()> {
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<Result<_, _>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            collect_audio_files(&path, files)?;
+            continue;
+        }
+        let is_audio = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()));
+        if !is_audio {
+            continue;
+        }
+        match song_from_file(&path) {
+            Ok(song) => files.push((song, path)),
+            Err(e) => eprintln!("bard: {:#}", e),
+        }
+    }
+    Ok(())
+}
+
+/// 从音频文件的标签构造歌曲信息
+fn song_from_file(path: &Path) -> Result<SongInfo> {
+    let tagged = lofty::read_from_path(path)
+        .with_context(|| format!("Failed to read tags of {}", path.display()))?;
+    let tag = tagged.primary_tag().or(tagged.first_tag());
+    let text = |value: Option<std::borrow::Cow<str>>| value.map(|v| v.trim().to_string());
+    let length = tagged.properties().duration().as_secs_f64();
+    Ok(SongInfo {
+        id: path.display().to_string(),
+        artist: tag.and_then(|t| text(t.artist())).unwrap_or_default(),
+        title: tag.and_then(|t| text(t.title())).unwrap_or_default(),
+        album: tag.and_then(|t| text(t.album())),
+        album_artist: None,
+        length: (length > 0.0).then_some(length),
+        track_id: None,
+        track_number: tag.and_then(|t| t.track()).map(|n| n as i32),
+        art_url: None,
+        position: 0.0,
+        rate: 1.0,
+        status: SongStatus::Stopped,
+        url: None,
+        player: String::new(),
+        identity: String::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lofty::config::WriteOptions;
+    use lofty::tag::{Tag, TagExt, TagType};
+    use serde_json::json;
+    use shared::testing::{TestServer, silent_wav, temp_path};
+
+    /// 带艺术家和标题的静音 WAV
+    fn write_wav(path: &Path, title: &str, millis: usize) {
+        std::fs::write(path, silent_wav(millis)).unwrap();
+        let mut tag = Tag::new(TagType::RiffInfo);
+        tag.set_artist("Artist".to_string());
+        tag.set_title(title.to_string());
+        tag.save_to_path(path, WriteOptions::default()).unwrap();
+    }
+
+    /// 音频文件和同名的 LRC 歌词
+    fn write_track(dir: &Path, title: &str, millis: usize, lyrics: bool) {
+        let stem = dir.join(title);
+        write_wav(&stem.with_extension("wav"), title, millis);
+        if lyrics {
+            let lrc = format!("[00:00.50]{} line one\n[00:01.50]line two\n", title);
+            std::fs::write(stem.with_extension("lrc"), lrc).unwrap();
+        }
+    }
+
+    fn lrclib_server() -> TestServer {
+        TestServer::start(|request| match request.path.as_str() {
+            "/api/get" if request.query("track_name") == Some("Existing") => (
+                200,
+                json!({ "duration": 3.0, "syncedLyrics": "[00:00.50]remote" }).to_string(),
+            ),
+            "/api/get" => (404, json!({ "code": 404 }).to_string()),
+            // 模糊搜索总能找到点什么，发布时不能据此跳过
+            "/api/search" => (
+                200,
+                json!([{ "duration": 3.0, "syncedLyrics": "[00:00.50]other" }]).to_string(),
+            ),
+            "/api/request-challenge" => (
+                200,
+                json!({ "prefix": "abc", "target": format!("0f{}", "f".repeat(62)) }).to_string(),
+            ),
+            "/api/publish" if request.body.contains("\"Broken\"") => {
+                (500, json!({ "message": "database error" }).to_string())
+            }
+            "/api/publish" => (201, String::new()),
+            _ => (404, String::new()),
+        })
+    }
+
+    fn publish_args(server: &TestServer, dir: &Path) -> PublishArgs {
+        PublishArgs {
+            dry_run: false,
+            base_url: Some(server.url.clone()),
+            paths: vec![dir.to_path_buf()],
+        }
+    }
+
+    fn local_config() -> Config {
+        let mut config = Config::default();
+        config.lyrics.sidecar_templates = vec!["{dir}/{stem}.lrc".to_string()];
+        config
+    }
+
+    #[test]
+    fn publishes_directory() {
+        let dir = temp_path("publish");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        write_track(&dir, "Broken", 3000, true);
+        write_track(&dir, "Existing", 3000, true);
+        write_track(&dir, "New", 3000, true);
+        write_track(&dir, "NoLyrics", 3000, false);
+        // 不到半秒，取整后没有时长
+        write_track(&dir, "Short", 100, true);
+        std::fs::write(dir.join("notes.txt"), "not audio").unwrap();
+
+        let server = lrclib_server();
+        let summary = publish(&publish_args(&server, &dir), &local_config());
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(
+            summary.unwrap(),
+            Summary {
+                published: 1,
+                skipped: 3,
+                failed: 1,
+            }
+        );
+
+        assert_eq!(
+            server.paths(),
+            [
+                "/api/get",
+                "/api/request-challenge",
+                "/api/publish",
+                "/api/get",
+                "/api/get",
+                "/api/request-challenge",
+                "/api/publish",
+            ]
+        );
+        let requests = server.requests();
+        assert_eq!(requests[0].query("track_name"), Some("Broken"));
+        assert_eq!(requests[0].query("artist_name"), Some("Artist"));
+        assert_eq!(requests[0].query("album_name"), Some(""));
+        assert_eq!(requests[0].query("duration"), Some("3"));
+
+        let published: serde_json::Value = serde_json::from_str(&requests[6].body).unwrap();
+        assert_eq!(published["trackName"], "New");
+        assert_eq!(
+            published["syncedLyrics"],
+            "[00:00.50] New line one\n[00:01.50] line two"
+        );
+    }
+
+    #[test]
+    fn failures_are_an_error() {
+        let dir = temp_path("publish-failed");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        write_track(&dir, "Broken", 3000, true);
+
+        let server = lrclib_server();
+        let result = run(publish_args(&server, &dir), &local_config());
+        let _ = std::fs::remove_dir_all(&dir);
+        let error = result.unwrap_err();
+        assert_eq!(error.to_string(), "1 track(s) failed");
+    }
+}
@@ -0,0 +1,22 @@
+use anyhow::Result;
+use shared::config::{Config, ProviderKind};
+use shared::tidal::DeviceLogin;
+
+/// 通过设备码登录 Tidal，把令牌写入单独的令牌文件
+pub fn login(config: &Config) -> Result<()> {
+    let login = DeviceLogin::start(&config.lyrics.tidal.auth_url)?;
+    println!(
+        "Open {} and enter the code {}",
+        login.verification_uri, login.user_code
+    );
+    println!("Waiting for authorization...");
+    let tokens = login.wait()?;
+
+    let path = config.lyrics.tidal.token_path()?;
+    tokens.save(&path)?;
+    println!("Logged in, tokens saved to {}", path.display());
+    if !config.lyrics.providers.contains(&ProviderKind::Tidal) {
+        println!("Add \"tidal\" to lyrics.providers to fetch lyrics from Tidal");
+    }
+    Ok(())
+}
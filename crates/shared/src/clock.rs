@@ -0,0 +1,189 @@
+use std::time::{Duration, Instant, SystemTime};
+
+/// 播放器报告与预测之差小于该值时视为抖动，只做平滑修正
+const JITTER_SECONDS: f64 = 0.25;
+/// 抖动修正时向报告值靠拢的比例
+const JITTER_BLEND: f64 = 0.3;
+/// 墙上时间比单调时间多走超过该值，说明系统挂起过
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(2);
+
+/// 播放进度时钟
+///
+/// 在两次播放器更新之间按单调时间和播放速率（MPRIS `Rate`）推算当前位置。
+/// `Seeked`、播放状态或速率变化、较大的跳变以及系统挂起恢复后直接对齐报告值；
+/// 小的抖动则平滑吸收，避免歌词来回跳动。
+#[derive(Debug, Clone)]
+pub struct PlaybackClock {
+    position: f64,
+    rate: f64,
+    playing: bool,
+    anchor: Instant,
+    anchor_wall: SystemTime,
+}
+
+impl Default for PlaybackClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PlaybackClock {
+    pub fn new() -> Self {
+        Self {
+            position: 0.0,
+            rate: 1.0,
+            playing: false,
+            anchor: Instant::now(),
+            anchor_wall: SystemTime::now(),
+        }
+    }
+
+    /// 当前播放位置（秒）
+    pub fn now(&self) -> f64 {
+        if !self.playing {
+            return self.position;
+        }
+        (self.position + self.anchor.elapsed().as_secs_f64() * self.rate).max(0.0)
+    }
+
+    /// 播放器报告的新状态
+    pub fn update(&mut self, position: f64, rate: f64, playing: bool) {
+        let rate = if rate.is_finite() && rate > 0.0 {
+            rate
+        } else {
+            1.0
+        };
+        let predicted = self.now();
+        let error = position - predicted;
+        let smooth = self.playing
+            && playing
+            && rate == self.rate
+            && !self.resumed_from_suspend()
+            && error.abs() < JITTER_SECONDS;
+
+        let position = if smooth {
+            predicted + error * JITTER_BLEND
+        } else {
+            position
+        };
+        self.rate = rate;
+        self.playing = playing;
+        self.anchor_at(position);
+    }
+
+    /// `Seeked` 信号：直接跳到新位置
+    pub fn seek(&mut self, position: f64) {
+        self.anchor_at(position);
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    fn anchor_at(&mut self, position: f64) {
+        self.position = position.max(0.0);
+        self.anchor = Instant::now();
+        self.anchor_wall = SystemTime::now();
+    }
+
+    /// 单调时钟在挂起期间不走，墙上时间明显多走说明刚从挂起恢复，此时预测值不可信
+    fn resumed_from_suspend(&self) -> bool {
+        let monotonic = self.anchor.elapsed();
+        self.anchor_wall
+            .elapsed()
+            .is_ok_and(|wall| wall > monotonic + SUSPEND_THRESHOLD)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 允许测试执行本身花费的时间
+    const TOLERANCE: f64 = 0.02;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < TOLERANCE,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    /// `ago` 秒前从 `position` 开始以 `rate` 播放的时钟
+    fn playing_since(position: f64, rate: f64, ago: f64) -> PlaybackClock {
+        let mut clock = PlaybackClock::new();
+        clock.update(position, rate, true);
+        let ago = Duration::from_secs_f64(ago);
+        clock.anchor -= ago;
+        clock.anchor_wall -= ago;
+        clock
+    }
+
+    #[test]
+    fn playing_clock_advances_at_rate() {
+        assert_close(playing_since(10.0, 1.0, 2.0).now(), 12.0);
+        assert_close(playing_since(10.0, 1.5, 2.0).now(), 13.0);
+    }
+
+    #[test]
+    fn paused_clock_holds_position() {
+        let mut clock = playing_since(10.0, 1.0, 2.0);
+        clock.update(12.0, 1.0, false);
+        clock.anchor -= Duration::from_secs(5);
+        assert_eq!(clock.now(), 12.0);
+    }
+
+    #[test]
+    fn jitter_is_blended() {
+        let mut clock = playing_since(10.0, 1.0, 1.0);
+        clock.update(11.2, 1.0, true);
+        assert_close(clock.now(), 11.0 + 0.2 * JITTER_BLEND);
+    }
+
+    #[test]
+    fn large_jumps_are_taken_as_is() {
+        let mut clock = playing_since(10.0, 1.0, 1.0);
+        clock.update(11.0 + JITTER_SECONDS + 0.05, 1.0, true);
+        assert_close(clock.now(), 11.0 + JITTER_SECONDS + 0.05);
+    }
+
+    #[test]
+    fn rate_change_snaps_to_reported_position() {
+        let mut clock = playing_since(10.0, 1.0, 1.0);
+        clock.update(11.2, 2.0, true);
+        assert_close(clock.now(), 11.2);
+        assert_eq!(clock.rate(), 2.0);
+    }
+
+    #[test]
+    fn invalid_rate_falls_back_to_normal_speed() {
+        for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let mut clock = PlaybackClock::new();
+            clock.update(10.0, rate, true);
+            assert_eq!(clock.rate(), 1.0);
+        }
+    }
+
+    #[test]
+    fn resume_from_suspend_snaps_to_reported_position() {
+        let mut clock = playing_since(10.0, 1.0, 1.0);
+        // 挂起期间墙上时间多走了一分钟，单调时间没有
+        clock.anchor_wall -= Duration::from_secs(60);
+        assert!(clock.resumed_from_suspend());
+        clock.update(11.2, 1.0, true);
+        assert_close(clock.now(), 11.2);
+    }
+
+    #[test]
+    fn seek_jumps_directly() {
+        let mut clock = playing_since(10.0, 1.0, 1.0);
+        clock.seek(11.1);
+        assert_close(clock.now(), 11.1);
+        clock.seek(-3.0);
+        assert_close(clock.now(), 0.0);
+    }
+}
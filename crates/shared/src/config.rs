@@ -0,0 +1,399 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// bard 的配置文件，位于 `$XDG_CONFIG_HOME/bard/config.toml`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub player: PlayerConfig,
+    pub lyrics: LyricsConfig,
+    pub waybar: WaybarConfig,
+}
+
+/// 播放器选择规则
+///
+/// 模式不区分大小写，支持 `*` 通配，匹配 MPRIS 总线名去掉 `org.mpris.MediaPlayer2.`
+/// 前缀后的部分（`firefox` 同时匹配 `firefox.instance_1_42`）或播放器的 Identity。
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PlayerConfig {
+    /// 播放器来源，按顺序合并
+    pub sources: Vec<SourceKind>,
+    /// 只使用匹配的播放器，为空时不限制
+    pub players: Vec<String>,
+    /// 优先级，排在前面的播放器优先；同一优先级内跟随最近开始播放的播放器
+    pub priority: Vec<String>,
+    /// 忽略的播放器
+    pub blocklist: Vec<String>,
+    /// MPD 连接设置
+    pub mpd: MpdConfig,
+    /// mpv IPC 设置
+    pub mpv: MpvConfig,
+    /// 外部命令来源设置
+    pub command: CommandConfig,
+}
+
+/// 播放器来源
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceKind {
+    /// MPRIS D-Bus 播放器
+    Mpris,
+    /// 直接连接 MPD，不需要 mpDris2
+    Mpd,
+    /// mpv 的 JSON IPC
+    Mpv,
+    /// 外部命令，输出 JSON
+    Command,
+}
+
+/// 外部命令来源设置
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CommandConfig {
+    /// 通过 `sh -c` 运行的命令
+    pub command: String,
+    /// 运行间隔（秒）
+    pub interval: f64,
+}
+
+impl Default for CommandConfig {
+    fn default() -> Self {
+        Self {
+            command: String::new(),
+            interval: 1.0,
+        }
+    }
+}
+
+/// mpv IPC 设置，mpv 需要以 `--input-ipc-server=<socket>` 启动
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MpvConfig {
+    /// IPC 套接字路径
+    pub socket: String,
+}
+
+impl Default for MpvConfig {
+    fn default() -> Self {
+        Self {
+            socket: "/tmp/mpvsocket".to_string(),
+        }
+    }
+}
+
+/// MPD 连接设置
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct MpdConfig {
+    /// `host:port` 或 Unix 套接字路径，未设置时依次使用 `$MPD_HOST`/`$MPD_PORT` 和 `localhost:6600`
+    pub address: Option<String>,
+    pub password: Option<String>,
+    /// MPD 的音乐目录，用于把歌曲的相对路径映射为本地文件；未设置时通过 Unix 套接字向 MPD 查询
+    pub music_directory: Option<String>,
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self {
+            sources: vec![SourceKind::Mpris],
+            players: Vec::new(),
+            priority: Vec::new(),
+            blocklist: [
+                "firefox",
+                "chromium",
+                "chrome",
+                "brave",
+                "vivaldi",
+                "opera",
+                "microsoft-edge",
+                "kdeconnect",
+                "plasma-browser-integration",
+            ]
+            .map(String::from)
+            .to_vec(),
+            mpd: MpdConfig::default(),
+            mpv: MpvConfig::default(),
+            command: CommandConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LyricsConfig {
+    /// 外置歌词文件的路径模板，按顺序尝试
+    ///
+    /// 可用占位符：`{dir}` 音频所在目录、`{stem}` 音频文件名（不含扩展名）、
+    /// `{artist}`、`{title}`、`{album}`；开头的 `~` 展开为家目录。扩展名不区分大小写。
+    pub sidecar_templates: Vec<String>,
+    /// 先查找外置歌词文件，再读取内嵌标签（调换 `providers` 中两者的顺序）
+    pub sidecar_first: bool,
+    /// 内嵌多语言歌词时的语言偏好（ISO-639-2，例如 `["chi", "eng"]`），
+    /// 第一个匹配的作为主歌词，其次的作为翻译
+    pub preferred_languages: Vec<String>,
+    /// 按文件或目录指定歌词编码，例如 `"~/Music/CN" = "gbk"`，覆盖自动检测
+    pub encoding_overrides: BTreeMap<String, String>,
+    /// 歌词来源，按顺序查询
+    pub providers: Vec<ProviderKind>,
+    /// 多个来源都有歌词时如何选择
+    pub policy: LyricsPolicy,
+    /// 每个来源的默认超时（秒）
+    pub timeout: f64,
+    /// 按来源名称覆盖超时，例如 `script = 20.0`
+    pub timeouts: BTreeMap<String, f64>,
+    /// 缓存目录，默认 `$XDG_CACHE_HOME/bard/lyrics`
+    pub cache_dir: Option<String>,
+    /// 用户脚本
+    pub script: ScriptConfig,
+    /// LRCLIB 在线歌词
+    pub lrclib: LrclibConfig,
+    /// 网易云音乐在线歌词
+    pub netease: NeteaseConfig,
+    /// Tidal 在线歌词
+    pub tidal: TidalLyricsConfig,
+}
+
+/// 歌词来源
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    /// 音频文件内嵌的标签
+    Embedded,
+    /// 外置 .lrc 文件
+    Sidecar,
+    /// 本地缓存，保存脚本和在线来源的结果
+    Cache,
+    /// 用户脚本
+    Script,
+    /// LRCLIB 在线歌词库
+    Lrclib,
+    /// 网易云音乐
+    Netease,
+    /// Tidal，需要先登录
+    Tidal,
+}
+
+/// 选择歌词的策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LyricsPolicy {
+    /// 第一个找到的歌词
+    First,
+    /// 第一个同步歌词，都没有时使用第一个纯文本歌词
+    #[default]
+    FirstSynced,
+    /// 优先有逐字时间的歌词，其次同步歌词
+    PreferWordTiming,
+    /// 优先带翻译的同步歌词，其次同步歌词
+    PreferTranslation,
+}
+
+/// 用户脚本来源
+///
+/// 通过 `sh -c` 运行，歌曲信息以环境变量传入：`BARD_ARTIST`、`BARD_TITLE`、`BARD_ALBUM`、
+/// `BARD_LENGTH`（秒）、`BARD_PATH`（本地文件）、`BARD_URL`。标准输出为 LRC 文本，没有输出表示没有歌词。
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ScriptConfig {
+    pub command: String,
+}
+
+/// LRCLIB 在线歌词来源
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LrclibConfig {
+    /// 服务地址，可指向自建实例
+    pub base_url: String,
+}
+
+impl Default for LrclibConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://lrclib.net".to_string(),
+        }
+    }
+}
+
+/// 网易云音乐在线歌词来源
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NeteaseConfig {
+    /// 接口地址
+    pub base_url: String,
+}
+
+impl Default for NeteaseConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://music.163.com".to_string(),
+        }
+    }
+}
+
+/// Tidal 在线歌词来源，只对 Tidal 播放的曲目生效
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TidalLyricsConfig {
+    /// 登录和刷新令牌的地址
+    pub auth_url: String,
+    /// 歌词接口地址
+    pub api_url: String,
+    /// 查询歌词时使用的地区
+    pub country_code: String,
+    /// 保存登录令牌的文件，默认 `$XDG_CONFIG_HOME/bard/tidal.toml`
+    pub token_file: Option<String>,
+}
+
+impl TidalLyricsConfig {
+    pub fn token_path(&self) -> Result<PathBuf> {
+        match &self.token_file {
+            Some(file) => Ok(expand_home(file)),
+            None => Ok(config_dir()?.join("bard").join("tidal.toml")),
+        }
+    }
+}
+
+impl Default for TidalLyricsConfig {
+    fn default() -> Self {
+        Self {
+            auth_url: "https://auth.tidal.com".to_string(),
+            api_url: "https://api.tidal.com".to_string(),
+            country_code: "US".to_string(),
+            token_file: None,
+        }
+    }
+}
+
+impl Default for LyricsConfig {
+    fn default() -> Self {
+        Self {
+            sidecar_templates: vec![
+                "{dir}/{stem}.lrc".to_string(),
+                "{dir}/Lyrics/{stem}.lrc".to_string(),
+                "~/Music/Lyrics/{artist} - {title}.lrc".to_string(),
+            ],
+            sidecar_first: false,
+            preferred_languages: Vec::new(),
+            encoding_overrides: BTreeMap::new(),
+            providers: vec![
+                ProviderKind::Embedded,
+                ProviderKind::Sidecar,
+                ProviderKind::Cache,
+            ],
+            policy: LyricsPolicy::default(),
+            timeout: 10.0,
+            timeouts: BTreeMap::new(),
+            cache_dir: None,
+            script: ScriptConfig::default(),
+            lrclib: LrclibConfig::default(),
+            netease: NeteaseConfig::default(),
+            tidal: TidalLyricsConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WaybarConfig {
+    pub karaoke: KaraokeConfig,
+    /// 多个播放器时的显示方式
+    pub mode: DisplayMode,
+    /// `all` 模式下各播放器之间的分隔符
+    pub separator: String,
+    /// 暂停超过这么多分钟后隐藏，0 表示一直显示
+    pub hide_paused_after: u64,
+}
+
+impl Default for WaybarConfig {
+    fn default() -> Self {
+        Self {
+            karaoke: KaraokeConfig::default(),
+            mode: DisplayMode::default(),
+            separator: "  ".to_string(),
+            hide_paused_after: 0,
+        }
+    }
+}
+
+/// 多个播放器时的显示方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayMode {
+    /// 只显示选中的播放器
+    #[default]
+    Single,
+    /// 每个播放器一段，依次拼接
+    All,
+    /// 一次显示一个播放器，收到 SIGUSR2 时切换到下一个
+    Cycle,
+}
+
+/// 逐字高亮（卡拉 OK）样式
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct KaraokeConfig {
+    /// 有逐字时间时输出 Pango 标记高亮已唱部分
+    pub enabled: bool,
+    /// 已唱部分的 `<span>` 属性
+    pub sung_span: String,
+    /// 未唱部分的 `<span>` 属性
+    pub unsung_span: String,
+}
+
+impl Default for KaraokeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            sung_span: r#"fgalpha="100%""#.to_string(),
+            unsung_span: r#"fgalpha="50%""#.to_string(),
+        }
+    }
+}
+
+impl Config {
+    /// 读取配置文件，不存在时返回默认配置
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Could not read config {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("Could not parse config {}", path.display()))
+    }
+
+    pub fn path() -> Result<PathBuf> {
+        Ok(config_dir()?.join("bard").join("config.toml"))
+    }
+}
+
+/// 展开路径开头的 `~`
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => match std::env::var_os("HOME") {
+            Some(home) => PathBuf::from(home).join(rest.trim_start_matches('/')),
+            None => PathBuf::from(path),
+        },
+        _ => PathBuf::from(path),
+    }
+}
+
+/// 缓存目录 `$XDG_CACHE_HOME/bard`
+pub fn cache_dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_CACHE_HOME").filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir).join("bard"));
+    }
+    let home = std::env::var_os("HOME").context("Neither XDG_CACHE_HOME nor HOME is set")?;
+    Ok(PathBuf::from(home).join(".cache").join("bard"))
+}
+
+fn config_dir() -> Result<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        return Ok(PathBuf::from(dir));
+    }
+    let home = std::env::var_os("HOME").context("Neither XDG_CONFIG_HOME nor HOME is set")?;
+    Ok(PathBuf::from(home).join(".config"))
+}
@@ -0,0 +1,100 @@
+use std::path::PathBuf;
+
+/// 播放器与歌词查找的错误
+///
+/// 前端可以据此区分“没有播放器”“没有歌词”等不同状态。
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// 无法连接到 D-Bus 会话总线
+    #[error("Could not connect to D-Bus: {0}")]
+    DBusUnavailable(String),
+
+    /// 没有正在运行的播放器
+    #[error("Could not find any player")]
+    NoPlayer,
+
+    /// 与播放器通信失败
+    #[error("Player did not respond: {0}")]
+    PlayerUnavailable(String),
+
+    /// 播放器没有提供所需的元数据
+    #[error("Player metadata missing: {0}")]
+    MetadataMissing(String),
+
+    /// 音频或歌词文件无法读取
+    #[error("Could not read {}: {source}", path.display())]
+    FileUnreadable {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// 文件无法写入，例如歌词缓存
+    #[error("Could not write {}: {source}", path.display())]
+    FileUnwritable {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// 标签解析失败
+    #[error("Could not parse tags of {}: {message}", path.display())]
+    TagParse { path: PathBuf, message: String },
+
+    /// 所有来源都没有歌词
+    #[error("No lyrics found for {0}")]
+    NoLyrics(String),
+
+    /// 歌曲 URL 不是本地文件
+    #[error("Unsupported URL: {0}")]
+    UnsupportedUrl(String),
+
+    /// 歌词来源在限定时间内没有返回
+    #[error("Lyrics provider {0} timed out")]
+    Timeout(String),
+
+    /// 歌词来源查询失败，例如脚本出错
+    #[error("Lyrics provider {provider} failed: {message}")]
+    Provider { provider: String, message: String },
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+impl From<mpris::DBusError> for Error {
+    fn from(e: mpris::DBusError) -> Self {
+        Error::PlayerUnavailable(e.to_string())
+    }
+}
+
+impl From<mpris::FindingError> for Error {
+    fn from(e: mpris::FindingError) -> Self {
+        match e {
+            mpris::FindingError::NoPlayerFound => Error::NoPlayer,
+            mpris::FindingError::DBusError(e) => Error::DBusUnavailable(e.to_string()),
+        }
+    }
+}
+
+impl Error {
+    /// 歌词来源查询失败
+    pub fn provider(provider: &str, message: impl ToString) -> Self {
+        Error::Provider {
+            provider: provider.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    /// 将 lofty 的读取错误区分为 IO 错误和标签解析错误
+    pub(crate) fn from_lofty(path: PathBuf, e: lofty::error::LoftyError) -> Self {
+        match e.kind() {
+            lofty::error::ErrorKind::Io(io) => Error::FileUnreadable {
+                path,
+                source: std::io::Error::new(io.kind(), io.to_string()),
+            },
+            _ => Error::TagParse {
+                path,
+                message: e.to_string(),
+            },
+        }
+    }
+}
@@ -0,0 +1,16 @@
+use reqwest::blocking::Client;
+
+/// 所有在线请求使用的 User-Agent
+const USER_AGENT: &str = concat!(
+    "bard/",
+    env!("CARGO_PKG_VERSION"),
+    " (https://github.com/wind-mask/bard)"
+);
+
+/// 带 bard User-Agent 的 HTTP 客户端，LRCLIB、网易云和 Tidal 共用
+pub(crate) fn client() -> Client {
+    Client::builder()
+        .user_agent(USER_AGENT)
+        .build()
+        .unwrap_or_default()
+}
@@ -1,3 +1,15 @@
+pub mod clock;
+pub mod config;
+pub mod error;
 pub mod lyrics;
 pub mod models;
 pub mod player;
+pub mod tidal;
+pub mod uri;
+
+mod http;
+
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+
+pub use error::{Error, Result};
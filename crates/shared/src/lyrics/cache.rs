@@ -0,0 +1,61 @@
+use std::path::PathBuf;
+
+use crate::error::{Error, Result};
+use crate::lyrics::charset::decode_lyrics_bytes;
+use crate::lyrics::format::format_lrc;
+use crate::lyrics::parser::parse_lyrics;
+use crate::lyrics::provider::{LyricsProvider, LyricsQuery};
+use crate::models::{Lyrics, SongInfo};
+
+/// 本地歌词缓存，保存脚本和在线来源找到的歌词
+///
+/// 每首歌一个 LRC 文件，文件名为 `艺术家 - 标题 [时长].lrc`。
+pub struct CacheProvider {
+    dir: PathBuf,
+}
+
+impl CacheProvider {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// 写入缓存
+    pub fn store(&self, song: &SongInfo, lyrics: &Lyrics) -> Result<()> {
+        let path = self.path(song);
+        let unwritable = |source| Error::FileUnwritable {
+            path: path.clone(),
+            source,
+        };
+        std::fs::create_dir_all(&self.dir).map_err(unwritable)?;
+        std::fs::write(&path, format_lrc(lyrics)).map_err(unwritable)
+    }
+
+    fn path(&self, song: &SongInfo) -> PathBuf {
+        let mut name = format!("{} - {}", song.artist, song.title);
+        if let Some(length) = song.length {
+            name.push_str(&format!(" [{:.0}]", length));
+        }
+        // 标签中的 `/` 不能出现在文件名里
+        self.dir.join(format!("{}.lrc", name.replace('/', "_")))
+    }
+}
+
+impl LyricsProvider for CacheProvider {
+    fn name(&self) -> &str {
+        "cache"
+    }
+
+    fn fetch(&self, query: &LyricsQuery) -> Result<Lyrics> {
+        let path = self.path(&query.song);
+        let bytes = match std::fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                return Err(Error::NoLyrics(query.song.id.clone()));
+            }
+            Err(source) => return Err(Error::FileUnreadable { path, source }),
+        };
+        Some(parse_lyrics(&decode_lyrics_bytes(&bytes, None)))
+            .filter(|lyrics| !lyrics.lines.is_empty())
+            .ok_or_else(|| Error::NoLyrics(query.song.id.clone()))
+    }
+}
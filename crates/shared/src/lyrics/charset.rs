@@ -0,0 +1,174 @@
+use std::path::Path;
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, WINDOWS_1252};
+
+use crate::config::{LyricsConfig, expand_home};
+
+/// 将歌词文件的原始字节转为文本
+///
+/// 依次使用：配置中的编码覆盖、BOM、UTF-16 无 BOM 的零字节特征、UTF-8 校验、chardetng 检测。
+pub fn decode_lyrics_bytes(bytes: &[u8], forced: Option<&'static Encoding>) -> String {
+    if let Some(encoding) = forced {
+        return encoding.decode(bytes).0.into_owned();
+    }
+    if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+        return encoding
+            .decode_without_bom_handling(&bytes[bom_len..])
+            .0
+            .into_owned();
+    }
+    if let Some(encoding) = sniff_utf16(bytes) {
+        return encoding.decode_without_bom_handling(bytes).0.into_owned();
+    }
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return text.to_string();
+    }
+    detect(bytes)
+        .decode_without_bom_handling(bytes)
+        .0
+        .into_owned()
+}
+
+/// 修复标签中被当作 Latin-1 解码的本地编码文本（常见于 GBK/Shift-JIS 的 ID3 标签）
+pub fn repair_tag_text(text: &str, forced: Option<&'static Encoding>) -> String {
+    let is_latin1 = text.chars().all(|c| (c as u32) <= 0xFF);
+    let has_high = text.chars().any(|c| (c as u32) >= 0x80);
+    if !is_latin1 || !has_high {
+        return text.to_string();
+    }
+    let bytes: Vec<u8> = text.chars().map(|c| c as u32 as u8).collect();
+    let encoding = forced.unwrap_or_else(|| detect(&bytes));
+    if encoding == WINDOWS_1252 {
+        return text.to_string();
+    }
+    let (decoded, _, had_errors) = encoding.decode(&bytes);
+    if had_errors {
+        text.to_string()
+    } else {
+        decoded.into_owned()
+    }
+}
+
+/// 查找路径对应的编码覆盖，取最长匹配的文件或目录
+pub fn encoding_override(config: &LyricsConfig, path: &Path) -> Option<&'static Encoding> {
+    config
+        .encoding_overrides
+        .iter()
+        .map(|(prefix, label)| (expand_home(prefix), label))
+        .filter(|(prefix, _)| path.starts_with(prefix))
+        .max_by_key(|(prefix, _)| prefix.components().count())
+        .and_then(|(prefix, label)| {
+            let encoding = Encoding::for_label(label.as_bytes());
+            if encoding.is_none() {
+                eprintln!(
+                    "bard: unknown encoding '{}' for {}",
+                    label,
+                    prefix.display()
+                );
+            }
+            encoding
+        })
+}
+
+fn detect(bytes: &[u8]) -> &'static Encoding {
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(None, true)
+}
+
+/// 无 BOM 的 UTF-16：ASCII 字符的高字节为零，集中出现在奇数或偶数位置
+fn sniff_utf16(bytes: &[u8]) -> Option<&'static Encoding> {
+    if bytes.len() < 4 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let pairs = bytes.len() / 2;
+    let even_zeros = bytes.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_zeros = bytes.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+    if odd_zeros * 3 > pairs && even_zeros * 10 < pairs {
+        Some(UTF_16LE)
+    } else if even_zeros * 3 > pairs && odd_zeros * 10 < pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use encoding_rs::{GBK, SHIFT_JIS};
+
+    const LRC: &str =
+        "[00:01.00]你好世界\n[00:03.00]我们一起唱歌\n[00:05.00]明天会更好\n[00:07.00]再见朋友";
+
+    fn utf16(text: &str, to_bytes: fn(u16) -> [u8; 2]) -> Vec<u8> {
+        text.encode_utf16().flat_map(to_bytes).collect()
+    }
+
+    #[test]
+    fn bom_decides_encoding() {
+        let mut utf8 = b"\xef\xbb\xbf".to_vec();
+        utf8.extend_from_slice(LRC.as_bytes());
+        assert_eq!(decode_lyrics_bytes(&utf8, None), LRC);
+
+        let mut le = b"\xff\xfe".to_vec();
+        le.extend(utf16(LRC, u16::to_le_bytes));
+        assert_eq!(decode_lyrics_bytes(&le, None), LRC);
+
+        let mut be = b"\xfe\xff".to_vec();
+        be.extend(utf16(LRC, u16::to_be_bytes));
+        assert_eq!(decode_lyrics_bytes(&be, None), LRC);
+    }
+
+    #[test]
+    fn utf16_without_bom() {
+        let text = "[00:01.00]hello\n[00:03.00]world";
+        let le = utf16(text, u16::to_le_bytes);
+        let be = utf16(text, u16::to_be_bytes);
+        assert_eq!(sniff_utf16(&le), Some(UTF_16LE));
+        assert_eq!(sniff_utf16(&be), Some(UTF_16BE));
+        assert_eq!(decode_lyrics_bytes(&le, None), text);
+        assert_eq!(decode_lyrics_bytes(&be, None), text);
+        // 普通文本没有零字节
+        assert_eq!(sniff_utf16(text.as_bytes()), None);
+    }
+
+    #[test]
+    fn gbk_is_detected() {
+        let (gbk, _, _) = GBK.encode(LRC);
+        assert!(std::str::from_utf8(&gbk).is_err());
+        assert_eq!(decode_lyrics_bytes(&gbk, None), LRC);
+    }
+
+    #[test]
+    fn forced_encoding_skips_detection() {
+        let (sjis, _, _) = SHIFT_JIS.encode("[00:01.00]こんにちは");
+        assert_eq!(
+            decode_lyrics_bytes(&sjis, Some(SHIFT_JIS)),
+            "[00:01.00]こんにちは"
+        );
+    }
+
+    #[test]
+    fn longest_override_wins() {
+        let mut config = LyricsConfig::default();
+        for (prefix, label) in [
+            ("/music", "gbk"),
+            ("/music/jp", "shift_jis"),
+            ("/music/jp/odd.lrc", "no-such-encoding"),
+        ] {
+            config
+                .encoding_overrides
+                .insert(prefix.to_string(), label.to_string());
+        }
+        let lookup = |path: &str| encoding_override(&config, Path::new(path));
+        assert_eq!(lookup("/music/cn/song.lrc"), Some(GBK));
+        assert_eq!(lookup("/music/jp/song.lrc"), Some(SHIFT_JIS));
+        // 按路径组件匹配，不是字符串前缀
+        assert_eq!(lookup("/music/jpop/song.lrc"), Some(GBK));
+        assert_eq!(lookup("/other/song.lrc"), None);
+        // 未知编码不回退到较短的匹配
+        assert_eq!(lookup("/music/jp/odd.lrc"), None);
+    }
+}
@@ -18,49 +18,133 @@ pub fn get_lyrics_status(lyrics: &[LyricLine], position: f64) -> LyricsStatus {
             let current_line = &lyrics[i];
 
             // 检查是否有下一行
-            let (next_line, next_timestamp, _) = if i < lyrics.len() - 1 {
+            let (next_line, next_timestamp) = if i < lyrics.len() - 1 {
                 let next = &lyrics[i + 1];
+                (next.text.clone(), Some(next.timestamp))
+            } else {
+                (String::new(), None)
+            };
 
-                {
-                    (
-                        next.text.clone(),
-                        Some(next.timestamp),
-                        current_line.translation.clone(),
-                    )
+            // 行结束时间：下一行开始，或最后一个字结束
+            let line_end = next_timestamp.or_else(|| {
+                current_line
+                    .words
+                    .as_ref()
+                    .and_then(|words| words.last())
+                    .and_then(|word| word.end)
+            });
+            let line_progress = progress(current_line.timestamp, line_end, adjusted_position);
+
+            let (current_word, word_progress, next_word_timestamp) = match &current_line.words {
+                Some(words) => {
+                    let index = words
+                        .iter()
+                        .take_while(|word| word.timestamp <= adjusted_position)
+                        .count()
+                        .checked_sub(1);
+                    let word_progress = index.map_or(0.0, |w| {
+                        let word = &words[w];
+                        let end = word
+                            .end
+                            .or_else(|| words.get(w + 1).map(|next| next.timestamp))
+                            .or(line_end);
+                        progress(word.timestamp, end, adjusted_position)
+                    });
+                    let next_word = words.get(index.map_or(0, |w| w + 1));
+                    (index, word_progress, next_word.map(|word| word.timestamp))
                 }
-            } else {
-                (String::new(), None, None)
+                None => (None, 0.0, None),
             };
 
             LyricsStatus {
                 current_line: current_line.clone(),
                 next_line,
                 next_timestamp,
+                current_word,
+                word_progress,
+                next_word_timestamp,
+                line_progress,
             }
         }
         None => {
             // No current line found, check if there's an upcoming line
-            if !lyrics.is_empty() {
-                LyricsStatus {
-                    current_line: LyricLine {
-                        timestamp: 0.0,
-                        text: String::new(),
-                        translation: None,
-                    },
-                    next_line: lyrics[0].text.clone(),
-                    next_timestamp: Some(lyrics[0].timestamp),
-                }
-            } else {
-                LyricsStatus {
-                    current_line: LyricLine {
-                        timestamp: 0.0,
-                        text: String::new(),
-                        translation: None,
-                    },
-                    next_line: String::new(),
-                    next_timestamp: None,
-                }
+            let (next_line, next_timestamp) = match lyrics.first() {
+                Some(first) => (first.text.clone(), Some(first.timestamp)),
+                None => (String::new(), None),
+            };
+            LyricsStatus {
+                current_line: LyricLine::default(),
+                next_line,
+                next_timestamp,
+                current_word: None,
+                word_progress: 0.0,
+                next_word_timestamp: None,
+                line_progress: 0.0,
             }
         }
     }
 }
+
+/// 计算 `position` 在 `[start, end)` 区间内的比例；结束时间未知时视为刚开始
+fn progress(start: f64, end: Option<f64>, position: f64) -> f64 {
+    match end {
+        Some(end) if end > start => ((position - start) / (end - start)).clamp(0.0, 1.0),
+        _ => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lyrics::parser::parse_lyrics;
+
+    fn lines() -> Vec<LyricLine> {
+        parse_lyrics(
+            "[00:12.00]<00:12.00>Hel<00:12.30>lo <00:12.80>world<00:13.20>\n\
+             [00:14.00]next line",
+        )
+        .lines
+    }
+
+    /// 加上显示提前量，使 `adjusted_position` 等于 `seconds`
+    fn at(seconds: f64) -> f64 {
+        seconds + POSITION_OFFSET_SECONDS
+    }
+
+    #[test]
+    fn before_first_line() {
+        let status = get_lyrics_status(&lines(), at(5.0));
+        assert_eq!(status.current_line.text, "");
+        assert_eq!(status.next_line, "Hello world");
+        assert_eq!(status.next_timestamp, Some(12.0));
+        assert_eq!(status.current_word, None);
+    }
+
+    #[test]
+    fn word_progress_within_word() {
+        let status = get_lyrics_status(&lines(), at(12.15));
+        assert_eq!(status.current_line.text, "Hello world");
+        assert_eq!(status.next_line, "next line");
+        assert_eq!(status.current_word, Some(0));
+        assert!((status.word_progress - 0.5).abs() < 1e-6);
+        assert_eq!(status.next_word_timestamp, Some(12.3));
+        assert!((status.line_progress - 0.075).abs() < 1e-6);
+    }
+
+    #[test]
+    fn last_word_finishes_before_next_line() {
+        let status = get_lyrics_status(&lines(), at(13.5));
+        assert_eq!(status.current_word, Some(2));
+        assert_eq!(status.word_progress, 1.0);
+        assert_eq!(status.next_word_timestamp, None);
+    }
+
+    #[test]
+    fn line_without_words() {
+        let status = get_lyrics_status(&lines(), at(15.0));
+        assert_eq!(status.current_line.text, "next line");
+        assert_eq!(status.current_word, None);
+        assert_eq!(status.word_progress, 0.0);
+        assert_eq!(status.next_timestamp, None);
+    }
+}
@@ -0,0 +1,231 @@
+use std::path::Path;
+
+use encoding_rs::Encoding;
+use lofty::file::TaggedFileExt;
+use lofty::tag::{ItemKey, ItemValue, TagType};
+
+use crate::config::LyricsConfig;
+use crate::error::{Error, Result};
+use crate::lyrics::charset::{encoding_override, repair_tag_text};
+use crate::lyrics::parser::{merge_translation, parse_lyrics};
+use crate::lyrics::sylt::read_sylt_lyrics;
+use crate::models::Lyrics;
+
+/// 各格式中用于保存歌词、但 lofty 没有映射到 `ItemKey::Lyrics` 的键
+const EXTRA_LYRICS_KEYS: [&str; 3] = ["UNSYNCEDLYRICS", "UNSYNCED LYRICS", "LYRICS"];
+
+/// 读取音频文件中内嵌的歌词
+///
+/// 遍历所有标签（ID3v2、ID3v1、APE、Vorbis、MP4 …）中的全部歌词条目以及 SYLT 帧，
+/// 按 `preferred_languages` 选出主歌词，另一种语言的同步歌词作为翻译合并。
+/// 同一语言中同步歌词优先于纯文本歌词。
+pub fn read_embedded_lyrics(music_path: &Path, config: &LyricsConfig) -> Result<Lyrics> {
+    let forced = encoding_override(config, music_path);
+    let mut candidates = read_tag_lyrics(music_path, forced)?;
+    candidates.extend(read_sylt_lyrics(music_path, forced));
+    select_by_language(candidates, &config.preferred_languages)
+        .ok_or_else(|| Error::NoLyrics(music_path.display().to_string()))
+}
+
+/// 枚举所有标签中的歌词条目，保留语言代码
+fn read_tag_lyrics(music_path: &Path, forced: Option<&'static Encoding>) -> Result<Vec<Lyrics>> {
+    let tagged_file = lofty::read_from_path(music_path)
+        .map_err(|e| Error::from_lofty(music_path.to_path_buf(), e))?;
+    let mut texts: Vec<&str> = Vec::new();
+    let mut candidates = Vec::new();
+    let items = tagged_file
+        .tags()
+        .iter()
+        .flat_map(|tag| tag.items().map(move |item| (tag.tag_type(), item)));
+    for (tag_type, item) in items {
+        let is_lyrics = match item.key() {
+            ItemKey::Lyrics => true,
+            ItemKey::Unknown(key) => EXTRA_LYRICS_KEYS
+                .iter()
+                .any(|k| k.eq_ignore_ascii_case(key)),
+            _ => false,
+        };
+        let ItemValue::Text(text) = item.value() else {
+            continue;
+        };
+        // 同一份歌词可能同时写在多个标签里
+        if !is_lyrics || texts.contains(&text.as_str()) {
+            continue;
+        }
+        texts.push(text);
+
+        let mut lyrics = parse_lyrics(&tag_text(tag_type, text, forced));
+        if lyrics.lines.is_empty() {
+            continue;
+        }
+        lyrics.metadata.language = language_code(item.lang());
+        candidates.push(lyrics);
+    }
+    Ok(candidates)
+}
+
+/// 标签文本，只有 ID3 标签可能把本地编码当作 Latin-1 保存，其余格式规定为 UTF-8
+fn tag_text(tag_type: TagType, text: &str, forced: Option<&'static Encoding>) -> String {
+    if forced.is_some() || matches!(tag_type, TagType::Id3v1 | TagType::Id3v2) {
+        repair_tag_text(text, forced)
+    } else {
+        text.to_string()
+    }
+}
+
+/// ISO-639-2 语言代码，`XXX` 或空白表示未知
+pub(crate) fn language_code(lang: &[u8; 3]) -> Option<String> {
+    let code = std::str::from_utf8(lang).ok()?.trim_matches(['\0', ' ']);
+    if code.is_empty() || code.eq_ignore_ascii_case("xxx") {
+        return None;
+    }
+    Some(code.to_ascii_lowercase())
+}
+
+/// 按语言偏好选出主歌词，并把另一种语言的歌词按时间戳合并为翻译
+fn select_by_language(mut candidates: Vec<Lyrics>, preferred: &[String]) -> Option<Lyrics> {
+    let rank = |lyrics: &Lyrics| {
+        let language = lyrics
+            .metadata
+            .language
+            .as_deref()
+            .and_then(|lang| preferred.iter().position(|p| p.eq_ignore_ascii_case(lang)))
+            .unwrap_or(preferred.len());
+        (language, !lyrics.is_synced())
+    };
+
+    let main_index = (0..candidates.len()).min_by_key(|&i| rank(&candidates[i]))?;
+    let mut main = candidates.swap_remove(main_index);
+
+    if main.is_synced() {
+        let translation = candidates
+            .into_iter()
+            .filter(|lyrics| lyrics.is_synced())
+            .filter(|lyrics| lyrics.metadata.language != main.metadata.language)
+            .min_by_key(|lyrics| rank(lyrics));
+        if let Some(translation) = translation {
+            merge_translation(&mut main, &translation, 0.01);
+        }
+    }
+    Some(main)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{silent_wav, temp_path};
+    use lofty::TextEncoding;
+    use lofty::config::WriteOptions;
+    use lofty::id3::v2::{Frame, Id3v2Tag, UnsynchronizedTextFrame};
+    use lofty::tag::TagExt;
+
+    /// "你好世界" 的 GBK 字节被当作 Latin-1 解码
+    const GBK_AS_LATIN1: &str = "\u{c4}\u{e3}\u{ba}\u{c3}\u{ca}\u{c0}\u{bd}\u{e7}";
+
+    #[test]
+    fn id3_tags_are_repaired() {
+        let gbk = Some(encoding_rs::GBK);
+        assert_eq!(tag_text(TagType::Id3v2, GBK_AS_LATIN1, gbk), "你好世界");
+        assert_eq!(tag_text(TagType::Id3v1, GBK_AS_LATIN1, gbk), "你好世界");
+    }
+
+    #[test]
+    fn utf8_tags_are_kept() {
+        let text = "Café à la crème, déjà vu";
+        assert_eq!(tag_text(TagType::VorbisComments, text, None), text);
+        assert_eq!(tag_text(TagType::Mp4Ilst, text, None), text);
+        assert_eq!(tag_text(TagType::Ape, GBK_AS_LATIN1, None), GBK_AS_LATIN1);
+    }
+
+    #[test]
+    fn forced_encoding_applies_to_all_tags() {
+        let gbk = Some(encoding_rs::GBK);
+        assert_eq!(tag_text(TagType::Ape, GBK_AS_LATIN1, gbk), "你好世界");
+    }
+
+    fn lyrics(language: &str, text: &str) -> Lyrics {
+        let mut lyrics = parse_lyrics(text);
+        lyrics.metadata.language = Some(language.to_string());
+        lyrics
+    }
+
+    fn preferred(languages: &[&str]) -> Vec<String> {
+        languages.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn preferred_language_beats_synced() {
+        let candidates = vec![
+            lyrics("eng", "[00:01.00]hello"),
+            lyrics("jpn", "konnichiwa"),
+        ];
+        let main = select_by_language(candidates, &preferred(&["JPN"])).unwrap();
+        assert_eq!(main.metadata.language.as_deref(), Some("jpn"));
+        // 纯文本主歌词不合并翻译
+        assert_eq!(main.lines[0].translation, None);
+    }
+
+    #[test]
+    fn synced_wins_within_the_same_preference() {
+        let candidates = vec![
+            lyrics("eng", "hello"),
+            lyrics("jpn", "[00:01.00]konnichiwa"),
+            lyrics("chi", "[00:01.00]ni hao"),
+        ];
+        let main = select_by_language(candidates, &preferred(&["fra"])).unwrap();
+        assert!(main.is_synced());
+        assert_eq!(main.metadata.language.as_deref(), Some("jpn"));
+        assert_eq!(main.lines[0].translation.as_deref(), Some("ni hao"));
+    }
+
+    #[test]
+    fn other_synced_language_becomes_translation() {
+        let candidates = vec![
+            lyrics("chi", "[00:01.00]ni hao\n[00:03.00]zai jian"),
+            lyrics("eng", "[00:01.00]hello\n[00:03.00]goodbye"),
+            lyrics("eng", "[00:01.00]hi"),
+        ];
+        let main = select_by_language(candidates, &preferred(&["eng", "chi"])).unwrap();
+        let lines: Vec<_> = main
+            .lines
+            .iter()
+            .map(|line| (line.text.as_str(), line.translation.as_deref()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![("hello", Some("ni hao")), ("goodbye", Some("zai jian"))]
+        );
+        assert!(select_by_language(Vec::new(), &[]).is_none());
+    }
+
+    #[test]
+    fn identical_tag_texts_are_read_once() {
+        let path = temp_path("embedded.wav");
+        std::fs::write(&path, silent_wav(100)).unwrap();
+        let mut tag = Id3v2Tag::new();
+        let uslt = |language: &[u8; 3], description: &str, text: &str| {
+            Frame::UnsynchronizedText(UnsynchronizedTextFrame::new(
+                TextEncoding::UTF8,
+                *language,
+                description.to_string(),
+                text.to_string(),
+            ))
+        };
+        tag.insert(uslt(b"eng", "", "[00:01.00]hello"));
+        tag.insert(uslt(b"eng", "copy", "[00:01.00]hello"));
+        tag.insert(uslt(b"chi", "", "[00:01.00]ni hao"));
+        tag.save_to_path(&path, WriteOptions::default()).unwrap();
+
+        let candidates = read_tag_lyrics(&path, None);
+        let _ = std::fs::remove_file(&path);
+        let languages: Vec<_> = candidates
+            .unwrap()
+            .into_iter()
+            .map(|lyrics| lyrics.metadata.language)
+            .collect();
+        assert_eq!(
+            languages,
+            [Some("eng".to_string()), Some("chi".to_string())]
+        );
+    }
+}
@@ -1,26 +1,497 @@
-use lofty::file::TaggedFileExt;
-use lofty::tag::ItemKey;
+use std::path::Path;
+use std::sync::{Arc, mpsc};
+use std::thread;
+use std::time::Duration;
 
+use crate::config::{self, LyricsConfig, LyricsPolicy, ProviderKind};
+use crate::error::{Error, Result};
+use crate::lyrics::cache::CacheProvider;
+use crate::lyrics::charset::{decode_lyrics_bytes, encoding_override};
+use crate::lyrics::embedded::read_embedded_lyrics;
+use crate::lyrics::lrclib::LrclibProvider;
+use crate::lyrics::netease::NeteaseProvider;
 use crate::lyrics::parser::parse_lyrics;
-use crate::models::LyricLine;
+use crate::lyrics::provider::{LyricsProvider, LyricsQuery};
+use crate::lyrics::script::ScriptProvider;
+use crate::lyrics::sidecar::find_sidecar;
+use crate::lyrics::tidal::TidalProvider;
+use crate::models::Lyrics;
 use crate::models::SongInfo;
+use crate::uri::resolve_local_path;
 
-/// 从歌曲元数据中获取歌词
-pub fn get_lyrics(song: &SongInfo) -> Option<Vec<LyricLine>> {
-    if let Some(url) = &song.url {
-        // url like "file:///home/user/Music/Artist - Title.mp3"
-        let music_path = url.trim_start_matches("file://");
-        use lofty::read_from_path;
-        let lyrics = read_from_path(music_path)
-            .ok()
-            .as_ref()
-            .and_then(|tagged_file| tagged_file.primary_tag())
-            .and_then(|tag| tag.get_string(&ItemKey::Lyrics))
-            .and_then(|s| parse_lyrics(s).into());
-        if lyrics.is_some() {
-            return lyrics;
-        }
-    }
-    // No lyrics found
-    None
+/// 从配置的歌词来源中获取歌词
+///
+/// 都没有找到时返回最能说明原因的错误，例如 URL 不是本地文件、文件无法读取，
+/// 否则为 [`Error::NoLyrics`]。
+pub fn get_lyrics(song: &SongInfo, config: &LyricsConfig) -> Result<Lyrics> {
+    LyricsPipeline::from_config(config).fetch(song)
+}
+
+/// 按顺序查询歌词来源，并按策略选择结果
+pub struct LyricsPipeline {
+    providers: Vec<(Arc<dyn LyricsProvider>, Duration)>,
+    policy: LyricsPolicy,
+    /// 启用缓存时，可缓存来源的结果写入这里
+    cache: Option<Arc<CacheProvider>>,
+}
+
+impl LyricsPipeline {
+    pub fn from_config(config: &LyricsConfig) -> Self {
+        let mut kinds = config.providers.clone();
+        if config.sidecar_first {
+            let embedded = kinds.iter().position(|k| *k == ProviderKind::Embedded);
+            let sidecar = kinds.iter().position(|k| *k == ProviderKind::Sidecar);
+            if let (Some(embedded), Some(sidecar)) = (embedded, sidecar)
+                && embedded < sidecar
+            {
+                kinds.swap(embedded, sidecar);
+            }
+        }
+
+        let mut pipeline = Self {
+            providers: Vec::new(),
+            policy: config.policy,
+            cache: None,
+        };
+        for kind in kinds {
+            let provider: Arc<dyn LyricsProvider> = match kind {
+                ProviderKind::Embedded => Arc::new(EmbeddedProvider {
+                    config: config.clone(),
+                }),
+                ProviderKind::Sidecar => Arc::new(SidecarProvider {
+                    config: config.clone(),
+                }),
+                ProviderKind::Cache => {
+                    let dir = match &config.cache_dir {
+                        Some(dir) => config::expand_home(dir),
+                        None => match config::cache_dir() {
+                            Ok(dir) => dir.join("lyrics"),
+                            Err(e) => {
+                                eprintln!("bard: {:#}, lyrics cache disabled", e);
+                                continue;
+                            }
+                        },
+                    };
+                    let cache = Arc::new(CacheProvider::new(dir));
+                    pipeline.cache = Some(cache.clone());
+                    cache
+                }
+                ProviderKind::Script => Arc::new(ScriptProvider::new(
+                    config.script.command.clone(),
+                    provider_timeout(config, "script"),
+                )),
+                ProviderKind::Lrclib => Arc::new(LrclibProvider::new(&config.lrclib.base_url)),
+                ProviderKind::Netease => Arc::new(NeteaseProvider::new(&config.netease.base_url)),
+                ProviderKind::Tidal => Arc::new(TidalProvider::new(&config.tidal)),
+            };
+            pipeline.push(provider, config);
+        }
+        pipeline
+    }
+
+    /// 追加一个来源，超时取 `timeouts` 中的同名项或默认值
+    pub fn push(&mut self, provider: Arc<dyn LyricsProvider>, config: &LyricsConfig) {
+        let timeout = provider_timeout(config, provider.name());
+        self.providers.push((provider, timeout));
+    }
+
+    pub fn fetch(&self, song: &SongInfo) -> Result<Lyrics> {
+        // url like "file:///home/user/Music/Artist%20-%20Title.mp3"
+        let (path, url_error) = match song.url.as_deref().map(resolve_local_path) {
+            Some(Ok(path)) => (Some(path), None),
+            Some(Err(e)) => (None, Some(e)),
+            None => (None, Some(Error::MetadataMissing("xesam:url".to_string()))),
+        };
+        let query = LyricsQuery {
+            song: song.clone(),
+            path,
+        };
+        self.fetch_query(query, url_error)
+    }
+
+    /// 查询已知路径的本地文件，不使用歌曲 URL
+    pub fn fetch_local(&self, song: &SongInfo, path: &Path) -> Result<Lyrics> {
+        let query = LyricsQuery {
+            song: song.clone(),
+            path: Some(path.to_path_buf()),
+        };
+        self.fetch_query(query, None)
+    }
+
+    fn fetch_query(&self, query: LyricsQuery, url_error: Option<Error>) -> Result<Lyrics> {
+        let query = Arc::new(query);
+        let song = &query.song;
+        // 来源自身的错误比 URL 无法解析更能说明原因
+        let mut reason = None;
+
+        // (排名, 歌词, 是否写入缓存)，排名越小越好
+        let mut best: Option<(u8, Lyrics, bool)> = None;
+        for (provider, timeout) in &self.providers {
+            match fetch_with_timeout(provider.clone(), query.clone(), *timeout) {
+                Ok(lyrics) => {
+                    let rank = rank(self.policy, &lyrics);
+                    if best.as_ref().is_none_or(|(best, _, _)| rank < *best) {
+                        best = Some((rank, lyrics, provider.cacheable()));
+                    }
+                    if rank == 0 {
+                        break;
+                    }
+                }
+                Err(Error::NoLyrics(_)) => {}
+                Err(e) => {
+                    reason.get_or_insert(e);
+                }
+            }
+        }
+
+        let Some((_, lyrics, cacheable)) = best else {
+            return Err(reason
+                .or(url_error)
+                .unwrap_or_else(|| Error::NoLyrics(song.id.clone())));
+        };
+        if cacheable
+            && let Some(cache) = &self.cache
+            && let Err(e) = cache.store(song, &lyrics)
+        {
+            eprintln!("bard: Failed to cache lyrics: {}", e);
+        }
+        Ok(lyrics)
+    }
+}
+
+/// 来源的超时，取 `timeouts` 中的同名项或默认值
+fn provider_timeout(config: &LyricsConfig, name: &str) -> Duration {
+    let seconds = config.timeouts.get(name).copied().unwrap_or(config.timeout);
+    Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX)
+}
+
+/// 在单独的线程中查询，超时后放弃等待，查询线程由来源自己结束
+fn fetch_with_timeout(
+    provider: Arc<dyn LyricsProvider>,
+    query: Arc<LyricsQuery>,
+    timeout: Duration,
+) -> Result<Lyrics> {
+    let name = provider.name().to_string();
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = tx.send(provider.fetch(&query));
+    });
+    match rx.recv_timeout(timeout) {
+        Ok(result) => result,
+        Err(mpsc::RecvTimeoutError::Timeout) => Err(Error::Timeout(name)),
+        Err(mpsc::RecvTimeoutError::Disconnected) => Err(Error::provider(&name, "panicked")),
+    }
+}
+
+/// 歌词在策略下的排名，0 为最佳，找到后不再查询后面的来源
+fn rank(policy: LyricsPolicy, lyrics: &Lyrics) -> u8 {
+    let synced = lyrics.is_synced();
+    match policy {
+        LyricsPolicy::First => 0,
+        LyricsPolicy::FirstSynced => u8::from(!synced),
+        LyricsPolicy::PreferWordTiming => {
+            if lyrics.lines.iter().any(|line| line.words.is_some()) {
+                0
+            } else {
+                1 + u8::from(!synced)
+            }
+        }
+        LyricsPolicy::PreferTranslation => {
+            if synced && lyrics.lines.iter().any(|line| line.translation.is_some()) {
+                0
+            } else {
+                1 + u8::from(!synced)
+            }
+        }
+    }
+}
+
+/// 音频文件内嵌的歌词标签
+struct EmbeddedProvider {
+    config: LyricsConfig,
+}
+
+impl LyricsProvider for EmbeddedProvider {
+    fn name(&self) -> &str {
+        "embedded"
+    }
+
+    fn fetch(&self, query: &LyricsQuery) -> Result<Lyrics> {
+        match &query.path {
+            Some(path) => read_embedded_lyrics(path, &self.config),
+            None => Err(Error::NoLyrics(query.song.id.clone())),
+        }
+    }
+}
+
+/// 外置 .lrc 文件
+struct SidecarProvider {
+    config: LyricsConfig,
+}
+
+impl LyricsProvider for SidecarProvider {
+    fn name(&self) -> &str {
+        "sidecar"
+    }
+
+    fn fetch(&self, query: &LyricsQuery) -> Result<Lyrics> {
+        match find_sidecar(&query.song, query.path.as_deref(), &self.config) {
+            Some(path) => read_sidecar_lyrics(&path, &self.config),
+            None => Err(Error::NoLyrics(query.song.id.clone())),
+        }
+    }
+}
+
+/// 读取外置 .lrc 文件
+fn read_sidecar_lyrics(lrc_path: &Path, config: &LyricsConfig) -> Result<Lyrics> {
+    let bytes = std::fs::read(lrc_path).map_err(|source| Error::FileUnreadable {
+        path: lrc_path.to_path_buf(),
+        source,
+    })?;
+    let text = decode_lyrics_bytes(&bytes, encoding_override(config, lrc_path));
+    Some(parse_lyrics(&text))
+        .filter(|lyrics| !lyrics.lines.is_empty())
+        .ok_or_else(|| Error::NoLyrics(lrc_path.display().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{song, temp_path};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// 返回固定结果的来源，记录被查询的次数
+    struct Stub {
+        name: &'static str,
+        result: fn() -> Result<Lyrics>,
+        cacheable: bool,
+        calls: AtomicUsize,
+    }
+
+    impl LyricsProvider for Stub {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn fetch(&self, _query: &LyricsQuery) -> Result<Lyrics> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            (self.result)()
+        }
+
+        fn cacheable(&self) -> bool {
+            self.cacheable
+        }
+    }
+
+    fn stub(name: &'static str, result: fn() -> Result<Lyrics>) -> Arc<Stub> {
+        Arc::new(Stub {
+            name,
+            result,
+            cacheable: false,
+            calls: AtomicUsize::new(0),
+        })
+    }
+
+    /// 像在线来源一样结果写入缓存
+    fn remote_stub(name: &'static str, result: fn() -> Result<Lyrics>) -> Arc<Stub> {
+        Arc::new(Stub {
+            name,
+            result,
+            cacheable: true,
+            calls: AtomicUsize::new(0),
+        })
+    }
+
+    fn plain() -> Result<Lyrics> {
+        Ok(parse_lyrics("plain"))
+    }
+
+    fn synced() -> Result<Lyrics> {
+        Ok(parse_lyrics("[00:01.00]synced"))
+    }
+
+    fn word_timed() -> Result<Lyrics> {
+        Ok(parse_lyrics("[00:01.00]<00:01.00>word <00:01.50>timed"))
+    }
+
+    fn translated() -> Result<Lyrics> {
+        Ok(parse_lyrics("[00:01.00]translated\n[00:01.00]翻译"))
+    }
+
+    fn no_lyrics() -> Result<Lyrics> {
+        Err(Error::NoLyrics("song".to_string()))
+    }
+
+    fn failing() -> Result<Lyrics> {
+        Err(Error::provider("failing", "boom"))
+    }
+
+    fn stub_pipeline(policy: LyricsPolicy, providers: &[Arc<Stub>]) -> LyricsPipeline {
+        let config = LyricsConfig {
+            providers: Vec::new(),
+            policy,
+            ..LyricsConfig::default()
+        };
+        let mut pipeline = LyricsPipeline::from_config(&config);
+        for provider in providers {
+            pipeline.push(provider.clone(), &config);
+        }
+        pipeline
+    }
+
+    fn first_text(result: Result<Lyrics>) -> String {
+        result.unwrap().lines[0].text.clone()
+    }
+
+    fn names(pipeline: &LyricsPipeline) -> Vec<&str> {
+        pipeline.providers.iter().map(|(p, _)| p.name()).collect()
+    }
+
+    #[test]
+    fn rank_by_policy() {
+        let [plain, synced, words, translated] =
+            [plain, synced, word_timed, translated].map(|f| f().unwrap());
+        let ranks = |policy| [&plain, &synced, &words, &translated].map(|l| rank(policy, l));
+        assert_eq!(ranks(LyricsPolicy::First), [0, 0, 0, 0]);
+        assert_eq!(ranks(LyricsPolicy::FirstSynced), [1, 0, 0, 0]);
+        assert_eq!(ranks(LyricsPolicy::PreferWordTiming), [2, 1, 0, 1]);
+        assert_eq!(ranks(LyricsPolicy::PreferTranslation), [2, 1, 1, 0]);
+    }
+
+    #[test]
+    fn first_synced_stops_at_synced() {
+        let providers = [stub("a", plain), stub("b", synced), stub("c", synced)];
+        let pipeline = stub_pipeline(LyricsPolicy::FirstSynced, &providers);
+        assert_eq!(
+            first_text(pipeline.fetch_local(&song("A", "T"), "/a.mp3".as_ref())),
+            "synced"
+        );
+        let calls: Vec<usize> = providers
+            .iter()
+            .map(|p| p.calls.load(Ordering::SeqCst))
+            .collect();
+        assert_eq!(calls, [1, 1, 0]);
+    }
+
+    #[test]
+    fn first_takes_anything() {
+        let providers = [stub("a", no_lyrics), stub("b", plain), stub("c", synced)];
+        let pipeline = stub_pipeline(LyricsPolicy::First, &providers);
+        assert_eq!(
+            first_text(pipeline.fetch_local(&song("A", "T"), "/a.mp3".as_ref())),
+            "plain"
+        );
+        assert_eq!(providers[2].calls.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn best_result_when_nothing_is_ideal() {
+        let path = Path::new("/a.mp3");
+        let providers = [
+            stub("a", plain),
+            stub("b", translated),
+            stub("c", word_timed),
+        ];
+        let pipeline = stub_pipeline(LyricsPolicy::PreferWordTiming, &providers);
+        assert_eq!(
+            first_text(pipeline.fetch_local(&song("A", "T"), path)),
+            "word timed"
+        );
+
+        // 没有带翻译的，取排在前面的同步歌词
+        let providers = [stub("a", plain), stub("b", synced), stub("c", word_timed)];
+        let pipeline = stub_pipeline(LyricsPolicy::PreferTranslation, &providers);
+        assert_eq!(
+            first_text(pipeline.fetch_local(&song("A", "T"), path)),
+            "synced"
+        );
+        assert_eq!(providers[2].calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn sidecar_first_swaps_local_sources() {
+        let mut config = LyricsConfig {
+            providers: vec![
+                ProviderKind::Embedded,
+                ProviderKind::Script,
+                ProviderKind::Sidecar,
+            ],
+            timeouts: [("sidecar".to_string(), 2.5)].into(),
+            ..LyricsConfig::default()
+        };
+        assert_eq!(
+            names(&LyricsPipeline::from_config(&config)),
+            ["embedded", "script", "sidecar"]
+        );
+        config.sidecar_first = true;
+        let pipeline = LyricsPipeline::from_config(&config);
+        assert_eq!(names(&pipeline), ["sidecar", "script", "embedded"]);
+        assert_eq!(pipeline.providers[0].1, Duration::from_secs_f64(2.5));
+        assert_eq!(
+            pipeline.providers[1].1,
+            Duration::from_secs_f64(config.timeout)
+        );
+    }
+
+    #[test]
+    fn only_cacheable_results_are_cached() {
+        let dir = temp_path("fetcher-cache");
+        let config = LyricsConfig {
+            providers: vec![ProviderKind::Cache],
+            cache_dir: Some(dir.to_string_lossy().into_owned()),
+            ..LyricsConfig::default()
+        };
+        let song = song("Artist", "Title");
+        let cached = |pipeline: &LyricsPipeline| pipeline.fetch_local(&song, "/a.mp3".as_ref());
+
+        let mut pipeline = LyricsPipeline::from_config(&config);
+        pipeline.push(stub("local", synced), &config);
+        cached(&pipeline).unwrap();
+        let mut cache_only = LyricsPipeline::from_config(&config);
+        assert!(matches!(cached(&cache_only), Err(Error::NoLyrics(_))));
+
+        let remote = remote_stub("remote", translated);
+        cache_only.push(remote.clone(), &config);
+        cached(&cache_only).unwrap();
+        assert_eq!(remote.calls.load(Ordering::SeqCst), 1);
+
+        // 第二次从缓存读取，翻译也保存下来了
+        let lyrics = cached(&cache_only).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(remote.calls.load(Ordering::SeqCst), 1);
+        assert_eq!(lyrics.lines[0].translation.as_deref(), Some("翻译"));
+    }
+
+    #[test]
+    fn provider_errors_beat_url_errors() {
+        let mut remote = song("Artist", "Title");
+        remote.url = Some("https://example.com/a.mp3".to_string());
+
+        let pipeline = stub_pipeline(LyricsPolicy::FirstSynced, &[stub("a", no_lyrics)]);
+        assert!(matches!(
+            pipeline.fetch(&remote),
+            Err(Error::UnsupportedUrl(_))
+        ));
+        // 本地文件没有歌词
+        assert!(matches!(
+            pipeline.fetch_local(&remote, "/a.mp3".as_ref()),
+            Err(Error::NoLyrics(_))
+        ));
+
+        let providers = [
+            stub("a", no_lyrics),
+            stub("b", failing),
+            stub("c", no_lyrics),
+        ];
+        let pipeline = stub_pipeline(LyricsPolicy::FirstSynced, &providers);
+        assert!(matches!(
+            pipeline.fetch(&remote),
+            Err(Error::Provider { provider, .. }) if provider == "failing"
+        ));
+
+        // 找到歌词时忽略错误
+        let providers = [stub("a", failing), stub("b", plain)];
+        let pipeline = stub_pipeline(LyricsPolicy::FirstSynced, &providers);
+        assert_eq!(first_text(pipeline.fetch(&remote)), "plain");
+    }
 }
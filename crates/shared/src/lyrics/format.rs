@@ -0,0 +1,127 @@
+use crate::models::lyrics::{LyricLine, Lyrics};
+
+/// 将歌词写回 LRC 文本，能被 [`parse_lyrics`](crate::lyrics::parser::parse_lyrics) 原样读回
+///
+/// 逐字时间写成增强 LRC 的 `<mm:ss.xxx>`，翻译写成相同时间戳的下一行。
+/// 偏移已经应用在时间戳上，不再输出 `[offset:]`。纯文本歌词不带时间戳。
+pub fn format_lrc(lyrics: &Lyrics) -> String {
+    let mut out = String::new();
+    let metadata = &lyrics.metadata;
+    let tags = [
+        ("ar", &metadata.artist),
+        ("ti", &metadata.title),
+        ("al", &metadata.album),
+        ("au", &metadata.author),
+        ("by", &metadata.by),
+    ];
+    for (key, value) in tags {
+        if let Some(value) = value {
+            out.push_str(&format!("[{}:{}]\n", key, value));
+        }
+    }
+    if let Some(length) = metadata.length {
+        out.push_str(&format!("[length:{}]\n", format_timestamp(length)));
+    }
+    for (key, value) in &metadata.extra {
+        out.push_str(&format!("[{}:{}]\n", key, value));
+    }
+
+    let synced = lyrics.is_synced();
+    for line in &lyrics.lines {
+        if !synced {
+            out.push_str(&line.text);
+            out.push('\n');
+            continue;
+        }
+        let timestamp = format!("[{}]", format_timestamp(line.timestamp));
+        out.push_str(&timestamp);
+        out.push_str(&format_words(line));
+        out.push('\n');
+        if let Some(translation) = &line.translation {
+            out.push_str(&timestamp);
+            out.push_str(translation);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+fn format_words(line: &LyricLine) -> String {
+    let Some(words) = &line.words else {
+        return line.text.clone();
+    };
+    let mut text = String::new();
+    for (i, word) in words.iter().enumerate() {
+        text.push_str(&format!(
+            "<{}>{}",
+            format_timestamp(word.timestamp),
+            word.text
+        ));
+        // 下一个字开始时间不同时补上结束标记
+        let next_start = words.get(i + 1).map(|next| next.timestamp);
+        if let Some(end) = word.end.filter(|&end| Some(end) != next_start) {
+            text.push_str(&format!("<{}>", format_timestamp(end)));
+        }
+    }
+    text
+}
+
+/// `mm:ss.xxx`
+fn format_timestamp(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lyrics::parser::parse_lyrics;
+
+    #[test]
+    fn formatted_lyrics_parse_back() {
+        let original = parse_lyrics(
+            "[ar:Artist]\n[ti:Title]\n[re:tool]\n\
+             [00:01.50]<00:01.50>Hel<00:02.00>lo<00:02.40> <00:03.00>world<00:04.00>\n\
+             [00:01.50]你好世界\n\
+             [01:05.25]plain line\n",
+        );
+        let text = format_lrc(&original);
+        let parsed = parse_lyrics(&text);
+
+        assert_eq!(parsed.metadata.artist.as_deref(), Some("Artist"));
+        assert_eq!(parsed.metadata.title.as_deref(), Some("Title"));
+        assert_eq!(parsed.metadata.extra, original.metadata.extra);
+        assert_eq!(parsed.lines.len(), original.lines.len());
+        for (parsed, original) in parsed.lines.iter().zip(&original.lines) {
+            assert_eq!(parsed.timestamp, original.timestamp);
+            assert_eq!(parsed.text, original.text);
+            assert_eq!(parsed.translation, original.translation);
+            let words = |line: &LyricLine| {
+                line.words.as_ref().map(|words| {
+                    words
+                        .iter()
+                        .map(|w| (w.timestamp, w.end, w.text.clone()))
+                        .collect::<Vec<_>>()
+                })
+            };
+            assert_eq!(words(parsed), words(original));
+        }
+        assert_eq!(original.lines[0].translation.as_deref(), Some("你好世界"));
+        assert_eq!(original.lines[0].words.as_ref().map(Vec::len), Some(4));
+    }
+
+    #[test]
+    fn plain_lyrics_have_no_timestamps() {
+        let original = parse_lyrics("first\nsecond");
+        let text = format_lrc(&original);
+        assert_eq!(text, "first\nsecond\n");
+        let parsed = parse_lyrics(&text);
+        assert_eq!(parsed.lines.len(), 2);
+        assert!(!parsed.is_synced());
+    }
+}
@@ -0,0 +1,514 @@
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, Response};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::{Error, Result};
+use crate::http;
+use crate::lyrics::parser::parse_lyrics;
+use crate::lyrics::provider::{LyricsProvider, LyricsQuery, duration_matches};
+use crate::models::{Lyrics, SongInfo};
+
+/// 搜索结果允许的时长误差（秒），LRCLIB 的时长以整秒记录
+const DURATION_TOLERANCE: f64 = 2.0;
+
+/// LRCLIB (https://lrclib.net) 在线歌词
+///
+/// 先用 `/api/get` 精确匹配艺术家、标题、专辑和时长，没有结果时用 `/api/search` 搜索。
+pub struct LrclibProvider {
+    client: Client,
+    base_url: String,
+}
+
+/// `/api/get` 和 `/api/search` 返回的一条记录
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Record {
+    duration: Option<f64>,
+    #[serde(default)]
+    instrumental: bool,
+    plain_lyrics: Option<String>,
+    synced_lyrics: Option<String>,
+}
+
+impl Record {
+    fn synced(&self) -> Option<&str> {
+        self.synced_lyrics
+            .as_deref()
+            .filter(|s| !s.trim().is_empty())
+    }
+
+    /// 有同步歌词时用同步歌词，否则用纯文本
+    fn text(&self) -> Option<&str> {
+        self.synced().or(self
+            .plain_lyrics
+            .as_deref()
+            .filter(|s| !s.trim().is_empty()))
+    }
+}
+
+/// 发布到 LRCLIB 的一首歌
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PublishRequest {
+    pub track_name: String,
+    pub artist_name: String,
+    pub album_name: String,
+    /// 时长（秒）
+    pub duration: f64,
+    pub plain_lyrics: String,
+    /// 纯文本歌词时为空
+    pub synced_lyrics: String,
+}
+
+impl PublishRequest {
+    /// 缺少标题、艺术家或时长时返回 `None`
+    pub fn new(song: &SongInfo, lyrics: &Lyrics) -> Option<Self> {
+        if song.title.is_empty() || song.artist.is_empty() {
+            return None;
+        }
+        // LRCLIB 以整秒记录时长，不到半秒的曲目没有可用的时长
+        let duration = song
+            .length
+            .map(f64::round)
+            .filter(|length| *length >= 1.0)?;
+        let plain_lyrics = lyrics
+            .lines
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        // LRCLIB 只接受普通 LRC，去掉逐字时间、翻译和 ID 标签
+        let synced_lyrics = if lyrics.is_synced() {
+            lyrics
+                .lines
+                .iter()
+                .map(|line| {
+                    let centis = (line.timestamp.max(0.0) * 100.0).round() as u64;
+                    format!(
+                        "[{:02}:{:02}.{:02}] {}",
+                        centis / 6000,
+                        centis / 100 % 60,
+                        centis % 100,
+                        line.text
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            String::new()
+        };
+        Some(Self {
+            track_name: song.title.clone(),
+            artist_name: song.artist.clone(),
+            album_name: song.album.clone().unwrap_or_default(),
+            duration,
+            plain_lyrics,
+            synced_lyrics,
+        })
+    }
+}
+
+/// `/api/request-challenge` 返回的工作量证明题目
+#[derive(Debug, Deserialize)]
+struct Challenge {
+    prefix: String,
+    target: String,
+}
+
+/// 解题最多尝试的 nonce 数，LRCLIB 的题目平均只需要几千万次
+const MAX_NONCES: u64 = if cfg!(test) { 1 << 16 } else { 1 << 32 };
+
+/// 找到一个 nonce，使 `SHA256(prefix + nonce)` 不大于 `target`（十六进制）
+///
+/// 目标无效或尝试 [`MAX_NONCES`] 次仍未解出时返回 `None`。
+pub fn solve_challenge(prefix: &str, target: &str) -> Option<String> {
+    let target = (0..target.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(target.get(i..i + 2)?, 16).ok())
+        .collect::<Option<Vec<u8>>>()?;
+    (0..MAX_NONCES)
+        .map(|nonce| nonce.to_string())
+        .find(|nonce| {
+            let hash = Sha256::new()
+                .chain_update(prefix)
+                .chain_update(nonce)
+                .finalize();
+            hash.as_slice() <= target.as_slice()
+        })
+}
+
+impl LrclibProvider {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: http::client(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// 发布歌词，先在本地解出服务器给的工作量证明题目
+    pub fn publish(&self, request: &PublishRequest) -> Result<()> {
+        let challenge: Challenge = self
+            .client
+            .post(format!("{}/api/request-challenge", self.base_url))
+            .send()
+            .map_err(|e| Error::provider(self.name(), e))
+            .and_then(|response| self.check(response))?
+            .json()
+            .map_err(|e| Error::provider(self.name(), e))?;
+        let nonce = solve_challenge(&challenge.prefix, &challenge.target).ok_or_else(|| {
+            Error::provider(
+                self.name(),
+                format!("could not solve challenge with target {}", challenge.target),
+            )
+        })?;
+
+        let response = self
+            .client
+            .post(format!("{}/api/publish", self.base_url))
+            .header("X-Publish-Token", format!("{}:{}", challenge.prefix, nonce))
+            .json(request)
+            .send()
+            .map_err(|e| Error::provider(self.name(), e))?;
+        self.check(response).map(drop)
+    }
+
+    /// 把错误响应转换为错误，带上服务器返回的说明
+    fn check(&self, response: Response) -> Result<Response> {
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        #[derive(Deserialize)]
+        struct ErrorBody {
+            message: String,
+        }
+        Err(match response.json::<ErrorBody>() {
+            Ok(body) => Error::provider(self.name(), format!("{}: {}", status, body.message)),
+            Err(_) => Error::provider(self.name(), status),
+        })
+    }
+
+    /// 按发布请求的字段精确查询 LRCLIB 上已有的歌词，不做模糊搜索
+    ///
+    /// 记录存在但没有歌词（例如纯音乐）时返回空歌词。
+    pub fn existing(&self, request: &PublishRequest) -> Result<Option<Lyrics>> {
+        let record = self.get_record(
+            &request.artist_name,
+            &request.track_name,
+            &request.album_name,
+            request.duration,
+        )?;
+        Ok(record.map(|record| record.text().map(parse_lyrics).unwrap_or_default()))
+    }
+
+    /// 精确匹配，需要专辑和时长
+    fn get(&self, query: &LyricsQuery) -> Result<Option<Record>> {
+        let song = &query.song;
+        let (Some(album), Some(length)) = (&song.album, song.length) else {
+            return Ok(None);
+        };
+        self.get_record(&song.artist, &song.title, album, length)
+    }
+
+    fn get_record(
+        &self,
+        artist: &str,
+        title: &str,
+        album: &str,
+        duration: f64,
+    ) -> Result<Option<Record>> {
+        let duration = format!("{:.0}", duration);
+        let params = [
+            ("artist_name", artist),
+            ("track_name", title),
+            ("album_name", album),
+            ("duration", duration.as_str()),
+        ];
+        let response = self
+            .client
+            .get(format!("{}/api/get", self.base_url))
+            .query(&params)
+            .send()
+            .map_err(|e| Error::provider(self.name(), e))?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let response = response
+            .error_for_status()
+            .map_err(|e| Error::provider(self.name(), e))?;
+        response
+            .json()
+            .map(Some)
+            .map_err(|e| Error::provider(self.name(), e))
+    }
+
+    /// 模糊搜索，选时长相符的结果，优先同步歌词
+    fn search(&self, query: &LyricsQuery) -> Result<Option<Record>> {
+        let song = &query.song;
+        let params = [
+            ("artist_name", song.artist.as_str()),
+            ("track_name", song.title.as_str()),
+        ];
+        let records: Vec<Record> = self
+            .client
+            .get(format!("{}/api/search", self.base_url))
+            .query(&params)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json())
+            .map_err(|e| Error::provider(self.name(), e))?;
+        Ok(records
+            .into_iter()
+            .filter(|record| !record.instrumental && record.text().is_some())
+            .filter(|record| duration_matches(song, record.duration, DURATION_TOLERANCE))
+            .min_by_key(|record| record.synced().is_none()))
+    }
+}
+
+impl LyricsProvider for LrclibProvider {
+    fn name(&self) -> &str {
+        "lrclib"
+    }
+
+    fn fetch(&self, query: &LyricsQuery) -> Result<Lyrics> {
+        let no_lyrics = || Error::NoLyrics(query.song.id.clone());
+        if query.song.title.is_empty() {
+            return Err(no_lyrics());
+        }
+        let record = match self.get(query)? {
+            Some(record) => record,
+            None => self.search(query)?.ok_or_else(no_lyrics)?,
+        };
+        if record.instrumental {
+            return Err(no_lyrics());
+        }
+        let text = record.text().ok_or_else(no_lyrics)?;
+        Some(parse_lyrics(text))
+            .filter(|lyrics| !lyrics.lines.is_empty())
+            .ok_or_else(no_lyrics)
+    }
+
+    fn cacheable(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TestServer, song};
+
+    fn query(album: Option<&str>, length: f64) -> LyricsQuery {
+        let mut song = song("Artist", "Title");
+        song.album = album.map(str::to_string);
+        song.length = Some(length);
+        LyricsQuery { song, path: None }
+    }
+
+    fn record(duration: f64, synced: Option<&str>, plain: Option<&str>) -> serde_json::Value {
+        serde_json::json!({
+            "duration": duration,
+            "instrumental": false,
+            "syncedLyrics": synced,
+            "plainLyrics": plain,
+        })
+    }
+
+    #[test]
+    fn exact_match() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/api/get" => (
+                200,
+                record(200.0, Some("[00:01.00]synced"), None).to_string(),
+            ),
+            _ => (500, String::new()),
+        });
+        let provider = LrclibProvider::new(&server.url);
+        let lyrics = provider.fetch(&query(Some("Album"), 200.4)).unwrap();
+        assert_eq!(lyrics.lines[0].text, "synced");
+        assert_eq!(lyrics.lines[0].timestamp, 1.0);
+
+        let request = &server.requests()[0];
+        assert_eq!(request.query("artist_name"), Some("Artist"));
+        assert_eq!(request.query("track_name"), Some("Title"));
+        assert_eq!(request.query("album_name"), Some("Album"));
+        assert_eq!(request.query("duration"), Some("200"));
+        assert!(request.header("user-agent").unwrap().starts_with("bard/"));
+        assert_eq!(server.paths(), ["/api/get"]);
+    }
+
+    #[test]
+    fn not_found_falls_back_to_search() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/api/get" => (404, r#"{"code":404,"name":"TrackNotFound"}"#.to_string()),
+            "/api/search" => {
+                let records = [
+                    record(200.0, None, Some("plain")),
+                    record(201.0, Some("[00:02.00]synced"), Some("plain")),
+                ];
+                (200, serde_json::json!(records).to_string())
+            }
+            _ => (500, String::new()),
+        });
+        let provider = LrclibProvider::new(&server.url);
+        let lyrics = provider.fetch(&query(Some("Album"), 200.0)).unwrap();
+        // 同步歌词优先
+        assert_eq!(lyrics.lines[0].text, "synced");
+        assert_eq!(server.paths(), ["/api/get", "/api/search"]);
+        assert_eq!(server.requests()[1].query("album_name"), None);
+    }
+
+    #[test]
+    fn search_filters_by_duration() {
+        let server = TestServer::start(|_| {
+            let records = [
+                record(
+                    200.0 + DURATION_TOLERANCE + 0.5,
+                    Some("[00:01.00]other"),
+                    None,
+                ),
+                record(200.0 - DURATION_TOLERANCE + 0.5, None, Some("close")),
+            ];
+            (200, serde_json::json!(records).to_string())
+        });
+        let provider = LrclibProvider::new(&server.url);
+        // 没有专辑时直接搜索
+        let lyrics = provider.fetch(&query(None, 200.0)).unwrap();
+        assert_eq!(lyrics.lines[0].text, "close");
+        assert!(!lyrics.is_synced());
+        assert_eq!(server.paths(), ["/api/search"]);
+
+        let server = TestServer::start(|_| {
+            let records = [record(300.0, Some("[00:01.00]other"), None)];
+            (200, serde_json::json!(records).to_string())
+        });
+        let provider = LrclibProvider::new(&server.url);
+        let result = provider.fetch(&query(None, 200.0));
+        assert!(matches!(result, Err(Error::NoLyrics(_))));
+    }
+
+    #[test]
+    fn instrumental_has_no_lyrics() {
+        let server = TestServer::start(|_| {
+            let record = serde_json::json!({
+                "duration": 200.0,
+                "instrumental": true,
+                "syncedLyrics": null,
+                "plainLyrics": null,
+            });
+            (200, record.to_string())
+        });
+        let provider = LrclibProvider::new(&server.url);
+        let result = provider.fetch(&query(Some("Album"), 200.0));
+        assert!(matches!(result, Err(Error::NoLyrics(_))));
+        assert_eq!(server.paths(), ["/api/get"]);
+    }
+
+    fn hash(prefix: &str, nonce: &str) -> Vec<u8> {
+        Sha256::new()
+            .chain_update(prefix)
+            .chain_update(nonce)
+            .finalize()
+            .to_vec()
+    }
+
+    #[test]
+    fn challenge_hash_is_below_target() {
+        let target = format!("00{}", "f".repeat(62));
+        let nonce = solve_challenge("prefix", &target).unwrap();
+        let target_bytes = [vec![0], vec![0xff; 31]].concat();
+        assert!(hash("prefix", &nonce) <= target_bytes);
+
+        // 目标比哈希短时，哈希的第一个字节必须更小
+        let nonce = solve_challenge("prefix", "0f").unwrap();
+        assert!(hash("prefix", &nonce)[0] < 0x0f);
+    }
+
+    #[test]
+    fn invalid_challenge_target() {
+        assert_eq!(solve_challenge("prefix", "fff"), None);
+        assert_eq!(solve_challenge("prefix", "zz"), None);
+        assert_eq!(solve_challenge("prefix", "0g"), None);
+    }
+
+    #[test]
+    fn unsolvable_challenge_gives_up() {
+        assert_eq!(solve_challenge("prefix", &"00".repeat(32)), None);
+    }
+
+    #[test]
+    fn publish_solves_challenge() {
+        const TARGET: &str = "0fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff";
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/api/request-challenge" => (
+                200,
+                serde_json::json!({ "prefix": "abc", "target": TARGET }).to_string(),
+            ),
+            "/api/publish" => (201, String::new()),
+            _ => (404, String::new()),
+        });
+        let mut song = song("Artist", "Title");
+        song.album = Some("Album".to_string());
+        song.length = Some(200.4);
+        let lyrics = parse_lyrics("[00:01.50]first\n[01:02.00]<00:01.50>second");
+        let request = PublishRequest::new(&song, &lyrics).unwrap();
+        LrclibProvider::new(&server.url).publish(&request).unwrap();
+
+        let requests = server.requests();
+        assert_eq!(server.paths(), ["/api/request-challenge", "/api/publish"]);
+        assert!(requests.iter().all(|r| r.method == "POST"));
+
+        let publish = &requests[1];
+        let token = publish.header("X-Publish-Token").unwrap();
+        let (prefix, nonce) = token.split_once(':').unwrap();
+        assert_eq!(prefix, "abc");
+        assert!(hash(prefix, nonce)[0] <= 0x0f);
+
+        let body: serde_json::Value = serde_json::from_str(&publish.body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "trackName": "Title",
+                "artistName": "Artist",
+                "albumName": "Album",
+                "duration": 200.0,
+                "plainLyrics": "first\nsecond",
+                "syncedLyrics": "[00:01.50] first\n[01:02.00] second",
+            })
+        );
+    }
+
+    #[test]
+    fn publish_reports_server_message() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/api/request-challenge" => (
+                200,
+                serde_json::json!({ "prefix": "abc", "target": "ff" }).to_string(),
+            ),
+            _ => (
+                400,
+                serde_json::json!({ "message": "Incorrect publish token" }).to_string(),
+            ),
+        });
+        let mut song = song("Artist", "Title");
+        song.length = Some(200.0);
+        let request = PublishRequest::new(&song, &parse_lyrics("plain")).unwrap();
+        let error = LrclibProvider::new(&server.url)
+            .publish(&request)
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("Incorrect publish token"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn server_errors_are_reported() {
+        let server = TestServer::start(|_| (500, String::new()));
+        let provider = LrclibProvider::new(&server.url);
+        let result = provider.fetch(&query(None, 200.0));
+        assert!(matches!(result, Err(Error::Provider { .. })));
+    }
+}
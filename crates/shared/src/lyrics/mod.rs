@@ -1,6 +1,18 @@
+pub mod cache;
+pub mod charset;
 pub mod display;
+pub mod embedded;
 pub mod fetcher;
+pub mod format;
+pub mod lrclib;
+pub mod netease;
 pub mod parser;
+pub mod provider;
+pub mod script;
+pub mod sidecar;
+pub mod sylt;
+pub mod tidal;
 
 pub use display::get_lyrics_status;
-pub use fetcher::get_lyrics;
+pub use fetcher::{LyricsPipeline, get_lyrics};
+pub use provider::{LyricsProvider, LyricsQuery};
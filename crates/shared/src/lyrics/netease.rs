@@ -0,0 +1,393 @@
+use reqwest::blocking::Client;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+
+use crate::error::{Error, Result};
+use crate::http;
+use crate::lyrics::parser::{merge_translation, parse_lyrics};
+use crate::lyrics::provider::{LyricsProvider, LyricsQuery, duration_matches};
+use crate::models::{LyricLine, LyricWord, Lyrics};
+
+/// 搜索结果允许的时长误差（秒），网易云上的版本常与本地文件差一两秒
+const DURATION_TOLERANCE: f64 = 3.0;
+
+/// 逐字歌词的行首时间和 LRC 可能有几百毫秒的差别，翻译按最接近的一行合并
+const YRC_TRANSLATION_TOLERANCE: f64 = 1.0;
+
+/// 网易云音乐在线歌词
+///
+/// 按标题和艺术家搜索，选时长相符的结果。有逐字歌词（YRC）时优先使用，
+/// 翻译（`tlyric`）按时间戳合并到每一行。
+pub struct NeteaseProvider {
+    client: Client,
+    base_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResponse {
+    result: Option<SearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResult {
+    #[serde(default)]
+    songs: Vec<Song>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Song {
+    id: u64,
+    name: String,
+    #[serde(default)]
+    artists: Vec<Artist>,
+    /// 时长（毫秒）
+    duration: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Artist {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LyricResponse {
+    lrc: Option<LyricText>,
+    tlyric: Option<LyricText>,
+    yrc: Option<LyricText>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LyricText {
+    lyric: Option<String>,
+}
+
+impl LyricText {
+    fn text(field: &Option<LyricText>) -> Option<&str> {
+        field
+            .as_ref()
+            .and_then(|t| t.lyric.as_deref())
+            .filter(|t| !t.trim().is_empty())
+    }
+}
+
+impl NeteaseProvider {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: http::client(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str, params: &[(&str, &str)]) -> Result<T> {
+        self.client
+            .get(format!("{}{}", self.base_url, path))
+            .header("Referer", "https://music.163.com/")
+            .query(params)
+            .send()
+            .and_then(|response| response.error_for_status())
+            .and_then(|response| response.json())
+            .map_err(|e| Error::provider(self.name(), e))
+    }
+
+    /// 搜索歌曲，优先标题和艺术家都相同的结果
+    fn search(&self, query: &LyricsQuery) -> Result<Option<u64>> {
+        let song = &query.song;
+        let keywords = format!("{} {}", song.title, song.artist);
+        let response: SearchResponse = self.get(
+            "/api/search/get",
+            &[("s", keywords.trim()), ("type", "1"), ("limit", "10")],
+        )?;
+        let songs = response.result.map(|r| r.songs).unwrap_or_default();
+
+        let same_artist = |candidate: &Song| {
+            candidate
+                .artists
+                .iter()
+                .any(|a| song.artist.to_lowercase().contains(&a.name.to_lowercase()))
+        };
+        Ok(songs
+            .iter()
+            .filter(|candidate| {
+                let duration = candidate.duration.map(|d| d / 1000.0);
+                duration_matches(song, duration, DURATION_TOLERANCE)
+            })
+            .min_by_key(|candidate| {
+                (
+                    !candidate.name.eq_ignore_ascii_case(&song.title),
+                    !same_artist(candidate),
+                )
+            })
+            .map(|candidate| candidate.id))
+    }
+}
+
+impl LyricsProvider for NeteaseProvider {
+    fn name(&self) -> &str {
+        "netease"
+    }
+
+    fn fetch(&self, query: &LyricsQuery) -> Result<Lyrics> {
+        let no_lyrics = || Error::NoLyrics(query.song.id.clone());
+        if query.song.title.is_empty() {
+            return Err(no_lyrics());
+        }
+        let id = self.search(query)?.ok_or_else(no_lyrics)?.to_string();
+        let response: LyricResponse = self.get(
+            "/api/song/lyric/v1",
+            &[("id", &id), ("lv", "-1"), ("tv", "-1"), ("yv", "-1")],
+        )?;
+
+        let (mut lyrics, tolerance) = match LyricText::text(&response.yrc).map(parse_yrc) {
+            Some(lyrics) if !lyrics.lines.is_empty() => (lyrics, YRC_TRANSLATION_TOLERANCE),
+            _ => {
+                let lrc = LyricText::text(&response.lrc).ok_or_else(no_lyrics)?;
+                (parse_lyrics(&strip_json_lines(lrc)), 0.01)
+            }
+        };
+        if lyrics.lines.is_empty() {
+            return Err(no_lyrics());
+        }
+        if let Some(tlyric) = LyricText::text(&response.tlyric) {
+            let translation = parse_lyrics(&strip_json_lines(tlyric));
+            if translation.is_synced() {
+                merge_translation(&mut lyrics, &translation, tolerance);
+            }
+        }
+        Ok(lyrics)
+    }
+
+    fn cacheable(&self) -> bool {
+        true
+    }
+}
+
+/// 去掉新版接口在歌词开头插入的 JSON 行（作词、作曲等信息）
+fn strip_json_lines(text: &str) -> String {
+    text.lines()
+        .filter(|line| !line.trim_start().starts_with('{'))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// 解析 YRC 逐字歌词
+///
+/// 每行形如 `[行开始,行时长](字开始,字时长,0)字(字开始,字时长,0)字…`，时间单位为毫秒。
+fn parse_yrc(text: &str) -> Lyrics {
+    let mut lines = Vec::new();
+    for line in text.lines().map(str::trim) {
+        let Some(rest) = line.strip_prefix('[') else {
+            continue;
+        };
+        let Some((times, rest)) = rest.split_once(']') else {
+            continue;
+        };
+        let Some((start, _)) = parse_millis_pair(times) else {
+            continue;
+        };
+
+        // 只有完整的 `(开始,时长,0)` 才是时间标签，歌词中的其他括号保留
+        let tags: Vec<(usize, usize, (f64, f64))> = rest
+            .match_indices('(')
+            .filter_map(|(i, _)| word_tag(&rest[i..]).map(|(times, len)| (i, i + len, times)))
+            .collect();
+        let words: Vec<LyricWord> = tags
+            .iter()
+            .enumerate()
+            .map(|(k, &(_, text_start, (word_start, duration)))| {
+                // 下一个时间标签之前都是当前字
+                let text_end = tags.get(k + 1).map_or(rest.len(), |next| next.0);
+                LyricWord {
+                    timestamp: word_start,
+                    end: Some(word_start + duration),
+                    text: rest[text_start..text_end].to_string(),
+                }
+            })
+            .collect();
+
+        let text: String = words.iter().map(|w| w.text.as_str()).collect();
+        if text.trim().is_empty() {
+            continue;
+        }
+        lines.push(LyricLine {
+            timestamp: start,
+            text,
+            translation: None,
+            words: Some(words),
+        });
+    }
+    lines.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    Lyrics {
+        lines,
+        ..Lyrics::default()
+    }
+}
+
+/// 开头的逐字时间标签 `(开始,时长,0)`，返回时间和标签的长度
+fn word_tag(text: &str) -> Option<((f64, f64), usize)> {
+    let inner = text.strip_prefix('(')?;
+    let fields = &inner[..inner.find(')')?];
+    let numeric = fields
+        .split(',')
+        .all(|f| !f.is_empty() && f.bytes().all(|b| b.is_ascii_digit()));
+    if !numeric || fields.split(',').count() != 3 {
+        return None;
+    }
+    Some((parse_millis_pair(fields)?, fields.len() + 2))
+}
+
+/// `开始,时长[,…]`（毫秒）转换为秒
+fn parse_millis_pair(text: &str) -> Option<(f64, f64)> {
+    let mut parts = text.split(',').map(|p| p.trim().parse::<f64>());
+    let start = parts.next()?.ok()?;
+    let duration = parts.next()?.ok()?;
+    Some((start / 1000.0, duration / 1000.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TestServer, song};
+    use serde_json::json;
+
+    /// 搜索返回 `songs`，歌词接口按 id 返回 `lyrics`
+    fn server(songs: serde_json::Value, lyrics: serde_json::Value) -> TestServer {
+        TestServer::start(move |request| match request.path.as_str() {
+            "/api/search/get" => (200, json!({ "result": { "songs": songs } }).to_string()),
+            "/api/song/lyric/v1" if request.query("id") == Some("2") => (200, lyrics.to_string()),
+            _ => (200, json!({ "code": 200 }).to_string()),
+        })
+    }
+
+    fn query(length: f64) -> LyricsQuery {
+        let mut song = song("Artist", "Title");
+        song.length = Some(length);
+        LyricsQuery { song, path: None }
+    }
+
+    #[test]
+    fn search_then_lyric() {
+        let songs = json!([
+            { "id": 1, "name": "Title", "artists": [{ "name": "Artist" }], "duration": 260000 },
+            { "id": 3, "name": "Title (Live)", "artists": [{ "name": "Artist" }], "duration": 200000 },
+            { "id": 2, "name": "Title", "artists": [{ "name": "Artist" }], "duration": 202000 },
+        ]);
+        let lyrics = json!({
+            "lrc": { "lyric": "{\"t\":0,\"c\":[{\"tx\":\"作词\"}]}\n[00:01.00]lrc line" },
+        });
+        let server = server(songs, lyrics);
+        let lyrics = NeteaseProvider::new(&server.url)
+            .fetch(&query(200.0))
+            .unwrap();
+        assert_eq!(lyrics.lines.len(), 1);
+        assert_eq!(lyrics.lines[0].text, "lrc line");
+
+        let requests = server.requests();
+        assert_eq!(server.paths(), ["/api/search/get", "/api/song/lyric/v1"]);
+        assert_eq!(requests[0].query("s"), Some("Title Artist"));
+        assert_eq!(requests[0].query("type"), Some("1"));
+        assert_eq!(requests[1].query("yv"), Some("-1"));
+        assert_eq!(
+            requests[1].header("referer"),
+            Some("https://music.163.com/")
+        );
+        assert!(
+            requests[0]
+                .header("user-agent")
+                .unwrap()
+                .starts_with("bard/")
+        );
+    }
+
+    #[test]
+    fn no_matching_duration() {
+        let songs = json!([{ "id": 2, "name": "Title", "duration": 260000 }]);
+        let server = server(songs, json!({}));
+        let result = NeteaseProvider::new(&server.url).fetch(&query(200.0));
+        assert!(matches!(result, Err(Error::NoLyrics(_))));
+        assert_eq!(server.paths(), ["/api/search/get"]);
+    }
+
+    #[test]
+    fn yrc_preferred_with_nearby_translation() {
+        let songs = json!([{ "id": 2, "name": "Title", "duration": 200000 }]);
+        let lyrics = json!({
+            "lrc": { "lyric": "[00:01.00]lrc line" },
+            "yrc": { "lyric": "[1300,2000](1300,1000,0)word (2300,1000,0)line" },
+            "tlyric": { "lyric": "[00:01.00]翻译" },
+        });
+        let server = server(songs, lyrics);
+        let lyrics = NeteaseProvider::new(&server.url)
+            .fetch(&query(200.0))
+            .unwrap();
+        let line = &lyrics.lines[0];
+        assert_eq!(line.text, "word line");
+        assert!(line.words.is_some());
+        // YRC 的行首比 LRC 晚 0.3 秒，翻译仍合并到这一行
+        assert_eq!(line.translation.as_deref(), Some("翻译"));
+    }
+
+    #[test]
+    fn lrc_translation_needs_same_timestamp() {
+        let songs = json!([{ "id": 2, "name": "Title", "duration": 200000 }]);
+        let lyrics = json!({
+            "lrc": { "lyric": "[00:01.00]one\n[00:05.00]two" },
+            "tlyric": { "lyric": "[00:01.00]一\n[00:05.30]二" },
+        });
+        let server = server(songs, lyrics);
+        let lyrics = NeteaseProvider::new(&server.url)
+            .fetch(&query(200.0))
+            .unwrap();
+        assert_eq!(lyrics.lines[0].translation.as_deref(), Some("一"));
+        assert_eq!(lyrics.lines[1].translation, None);
+    }
+
+    #[test]
+    fn json_lines_are_stripped() {
+        let text = "{\"t\":0,\"c\":[]}\n  {\"t\":1}\n[00:01.00]line\n[00:02.00]{not json}";
+        assert_eq!(
+            strip_json_lines(text),
+            "[00:01.00]line\n[00:02.00]{not json}"
+        );
+    }
+
+    fn words(line: &LyricLine) -> Vec<&str> {
+        line.words
+            .iter()
+            .flatten()
+            .map(|word| word.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn yrc_words_with_times() {
+        let lyrics = parse_yrc(
+            "[5000,2000](5000,400,0)Se(5400,600,0)cond\n\
+             [1000,3000](1000,500,0)First (1500,1500,0)line",
+        );
+        assert_eq!(lyrics.lines.len(), 2);
+        let first = &lyrics.lines[0];
+        assert_eq!(first.timestamp, 1.0);
+        assert_eq!(first.text, "First line");
+        assert_eq!(words(first), ["First ", "line"]);
+        let word = &first.words.as_ref().unwrap()[1];
+        assert_eq!((word.timestamp, word.end), (1.5, Some(3.0)));
+        assert_eq!(lyrics.lines[1].text, "Second");
+    }
+
+    #[test]
+    fn yrc_keeps_parentheses_in_text() {
+        let lyrics = parse_yrc("[1000,3000](1000,500,0)Hello (1500,500,0)(Oh (2000,500,0)yeah)");
+        let line = &lyrics.lines[0];
+        assert_eq!(line.text, "Hello (Oh yeah)");
+        assert_eq!(words(line), ["Hello ", "(Oh ", "yeah)"]);
+        // 不完整的标签也是歌词
+        let lyrics = parse_yrc("[0,1000](0,500,0)a (1,2)(500,500,0)b");
+        assert_eq!(words(&lyrics.lines[0]), ["a (1,2)", "b"]);
+    }
+
+    #[test]
+    fn yrc_skips_json_and_empty_lines() {
+        let lyrics = parse_yrc("{\"t\":0,\"c\":[{\"tx\":\"作词: \"}]}\n[0,1000](0,500,0) \n");
+        assert!(lyrics.lines.is_empty());
+    }
+}
@@ -1,83 +1,481 @@
-use crate::models::lyrics::LyricLine;
+use crate::models::lyrics::{LyricLine, LyricWord, Lyrics, LyricsMetadata};
 use regex::Regex;
+use std::sync::LazyLock;
 
-pub fn parse_lyrics(lyrics_text: &str) -> Vec<LyricLine> {
+// 匹配行首的方括号标签，例如 [01:23.45] 或 [ar:歌手]
+static TAG_REGEX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*\[([^\]]*)\]").unwrap());
+// 匹配 ID 标签的内容，例如 ar:歌手、offset:+500
+static ID_TAG_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([A-Za-z#][A-Za-z0-9_#-]*)\s*:(.*)$").unwrap());
+static WORD_TIMESTAMP_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<(\d+:\d+(?:[.:]\d+)?)>").unwrap());
+
+pub fn parse_lyrics(lyrics_text: &str) -> Lyrics {
+    let mut metadata = LyricsMetadata::default();
     let mut lines = Vec::new();
     let lines_vec: Vec<&str> = lyrics_text.lines().collect();
 
-    // 正则表达式匹配时间戳行，例如 [01:23.456]歌词内容
-    let timestamp_regex = Regex::new(r"\[(\d+):(\d+)\.(\d+)\](.*)").unwrap();
-
     let mut i = 0;
     while i < lines_vec.len() {
         let line = lines_vec[i];
 
-        if let Some(caps) = timestamp_regex.captures(line) {
-            let minutes: f64 = caps.get(1).unwrap().as_str().parse().unwrap_or(0.0);
-            let seconds: f64 = caps.get(2).unwrap().as_str().parse().unwrap_or(0.0);
-            let centiseconds: f64 = caps.get(3).unwrap().as_str().parse().unwrap_or(0.0);
-            let content = caps.get(4).unwrap().as_str();
-
-            let timestamp = minutes * 60.0 + seconds + centiseconds / 1000.0;
-
-            // 获取纯文本（去掉时间戳标记）
-            let clean_text = extract_clean_text(content);
-
-            // 检查下一行是否是相同时间戳的翻译
-            let mut translation = None;
-            if i + 1 < lines_vec.len() {
-                let next_line = lines_vec[i + 1];
-                if let Some(next_caps) = timestamp_regex.captures(next_line) {
-                    let next_minutes: f64 =
-                        next_caps.get(1).unwrap().as_str().parse().unwrap_or(0.0);
-                    let next_seconds: f64 =
-                        next_caps.get(2).unwrap().as_str().parse().unwrap_or(0.0);
-                    let next_centiseconds: f64 =
-                        next_caps.get(3).unwrap().as_str().parse().unwrap_or(0.0);
-                    let next_timestamp =
-                        next_minutes * 60.0 + next_seconds + next_centiseconds / 1000.0;
-
-                    // 如果时间戳相同（允许很小的误差），认为是翻译
-                    if (next_timestamp - timestamp).abs() < 0.01 {
-                        let next_content = next_caps.get(4).unwrap().as_str();
-                        let next_clean_text = extract_clean_text(next_content);
-                        if !next_clean_text.is_empty() {
-                            translation = Some(next_clean_text);
-                            i += 1; // 跳过翻译行
+        if let Some((timestamps, content)) = parse_time_line(line) {
+            // 获取纯文本（去掉时间戳标记）和逐字时间
+            let (clean_text, words) = extract_words(content);
+
+            // 检查下一行是否是相同时间戳的翻译，压缩格式的每个时间戳分别配对
+            let mut translations = vec![None; timestamps.len()];
+            if let Some((next_timestamps, next_content)) =
+                lines_vec.get(i + 1).and_then(|l| parse_time_line(l))
+            {
+                let next_clean_text = extract_clean_text(next_content);
+                if !next_clean_text.is_empty() {
+                    let mut matched = false;
+                    for (timestamp, translation) in timestamps.iter().zip(&mut translations) {
+                        // 如果时间戳相同（允许很小的误差），认为是翻译
+                        if next_timestamps
+                            .iter()
+                            .any(|next| (next - timestamp).abs() < 0.01)
+                        {
+                            *translation = Some(next_clean_text.clone());
+                            matched = true;
                         }
                     }
+                    if matched {
+                        i += 1; // 跳过翻译行
+                    }
                 }
             }
 
             if !clean_text.is_empty() {
-                lines.push(LyricLine {
-                    timestamp,
-                    text: clean_text,
-                    translation,
-                });
+                let first_timestamp = timestamps[0];
+                for (timestamp, translation) in timestamps.into_iter().zip(translations) {
+                    // 逐字时间以第一个时间戳为准，其余副本按差值平移
+                    let delta = timestamp - first_timestamp;
+                    lines.push(LyricLine {
+                        timestamp,
+                        text: clean_text.clone(),
+                        translation,
+                        words: words.as_ref().map(|words| shift_words(words, delta)),
+                    });
+                }
             }
-        } else if !line.trim().is_empty() && !line.starts_with('[') {
+        } else if let Some((key, value)) = parse_id_tag(line) {
+            apply_id_tag(&mut metadata, key, value);
+        } else if !line.trim().is_empty() && !line.trim_start().starts_with('[') {
             // 对于非时间戳行（可能是纯文本歌词或翻译），如果不是翻译就添加
             lines.push(LyricLine {
                 timestamp: 0.0,
                 text: line.trim().to_string(),
                 translation: None,
+                words: None,
             });
         }
 
         i += 1;
     }
 
+    // 应用全局偏移：正值表示歌词提前显示
+    if metadata.offset != 0 {
+        let offset = metadata.offset as f64 / 1000.0;
+        for line in &mut lines {
+            line.timestamp = (line.timestamp - offset).max(0.0);
+            if let Some(words) = &mut line.words {
+                *words = shift_words(words, -offset);
+            }
+        }
+    }
+
     // 按时间戳排序
-    lines.sort_by(|a, b| a.timestamp.partial_cmp(&b.timestamp).unwrap());
-    lines
+    lines.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    Lyrics { metadata, lines }
+}
+
+/// 解析以时间标签开头的行，返回所有前导时间戳（秒）和剩余内容
+///
+/// 压缩格式 `[00:45.10][01:52.30]副歌` 会返回两个时间戳。
+fn parse_time_line(line: &str) -> Option<(Vec<f64>, &str)> {
+    let mut timestamps = Vec::new();
+    let mut rest = line;
+    while let Some(caps) = TAG_REGEX.captures(rest) {
+        let Some(timestamp) = parse_timestamp(&caps[1]) else {
+            break;
+        };
+        timestamps.push(timestamp);
+        rest = &rest[caps.get(0)?.end()..];
+    }
+    (!timestamps.is_empty()).then_some((timestamps, rest))
+}
+
+/// 解析时间标签内容，支持 `mm:ss`、`mm:ss.xx`、`mm:ss:xx`、`hh:mm:ss.xxx`
+///
+/// 小数部分按位数换算，`.5` 为 0.5 秒，`.34` 为 0.34 秒，`.034` 为 0.034 秒。
+pub fn parse_timestamp(tag: &str) -> Option<f64> {
+    let parts: Vec<&str> = tag.trim().split(':').collect();
+    let (hours, minutes, seconds, fraction) = match parts.as_slice() {
+        [mm, ss] => {
+            let (ss, frac) = split_fraction(ss);
+            ("0", *mm, ss, frac)
+        }
+        // hh:mm:ss.xxx
+        [hh, mm, ss] if ss.contains('.') => {
+            let (ss, frac) = split_fraction(ss);
+            (*hh, *mm, ss, frac)
+        }
+        // mm:ss:xx
+        [mm, ss, xx] => ("0", *mm, *ss, Some(*xx)),
+        _ => return None,
+    };
+
+    let hours = parse_digits(hours)?;
+    let minutes = parse_digits(minutes)?;
+    let seconds = parse_digits(seconds)?;
+    let fraction = match fraction {
+        Some(f) => parse_digits(f)? / 10f64.powi(f.len() as i32),
+        None => 0.0,
+    };
+
+    Some(hours * 3600.0 + minutes * 60.0 + seconds + fraction)
+}
+
+fn split_fraction(s: &str) -> (&str, Option<&str>) {
+    match s.split_once('.') {
+        Some((whole, frac)) => (whole, Some(frac)),
+        None => (s, None),
+    }
+}
+
+fn parse_digits(s: &str) -> Option<f64> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+/// 解析独占一行的 ID 标签，例如 `[ti:标题]`
+fn parse_id_tag(line: &str) -> Option<(&str, &str)> {
+    let caps = TAG_REGEX.captures(line)?;
+    if !line[caps.get(0)?.end()..].trim().is_empty() {
+        return None;
+    }
+    let inner = ID_TAG_REGEX.captures(caps.get(1)?.as_str())?;
+    Some((inner.get(1)?.as_str(), inner.get(2)?.as_str().trim()))
+}
+
+fn apply_id_tag(metadata: &mut LyricsMetadata, key: &str, value: &str) {
+    let text = || (!value.is_empty()).then(|| value.to_string());
+    match key.to_ascii_lowercase().as_str() {
+        "ar" => metadata.artist = text(),
+        "ti" => metadata.title = text(),
+        "al" => metadata.album = text(),
+        "au" => metadata.author = text(),
+        "by" => metadata.by = text(),
+        "length" => metadata.length = parse_timestamp(value),
+        "offset" => metadata.offset = value.trim_start_matches('+').parse().unwrap_or(0),
+        _ => metadata.extra.push((key.to_string(), value.to_string())),
+    }
+}
+
+/// 解析增强 LRC 的逐字时间，例如 `<00:12.00>Hel<00:12.30>lo <00:12.80>world<00:13.20>`
+///
+/// 返回纯文本和逐字列表；没有逐字标记时列表为 `None`。
+/// 第一个标记之前的文本没有独立时间，归入第一个字。
+fn extract_words(content: &str) -> (String, Option<Vec<LyricWord>>) {
+    let clean_text = extract_clean_text(content);
+    let markers: Vec<_> = WORD_TIMESTAMP_REGEX.captures_iter(content).collect();
+    if markers.is_empty() || clean_text.is_empty() {
+        return (clean_text, None);
+    }
+
+    let mut words: Vec<LyricWord> = Vec::new();
+    let mut leading = &content[..markers[0].get(0).unwrap().start()];
+    for (i, caps) in markers.iter().enumerate() {
+        let Some(timestamp) = parse_timestamp(&caps[1]) else {
+            continue;
+        };
+        let start = caps.get(0).unwrap().end();
+        let end = markers
+            .get(i + 1)
+            .map_or(content.len(), |next| next.get(0).unwrap().start());
+        let text = format!("{}{}", leading, &content[start..end]);
+        leading = "";
+
+        // 每个标记同时是上一个字的结束时间，末尾的空标记只表示结束
+        if let Some(last) = words.last_mut() {
+            last.end.get_or_insert(timestamp);
+        }
+        if !text.is_empty() {
+            words.push(LyricWord {
+                timestamp,
+                end: None,
+                text,
+            });
+        }
+    }
+
+    // 去掉首尾空白，保证拼接结果与纯文本一致
+    if let Some(first) = words.first_mut() {
+        first.text = first.text.trim_start().to_string();
+    }
+    if let Some(last) = words.last_mut() {
+        last.text = last.text.trim_end().to_string();
+    }
+    words.retain(|word| !word.text.is_empty());
+
+    if words.is_empty() {
+        (clean_text, None)
+    } else {
+        (clean_text, Some(words))
+    }
+}
+
+fn shift_words(words: &[LyricWord], delta: f64) -> Vec<LyricWord> {
+    words
+        .iter()
+        .map(|word| LyricWord {
+            timestamp: (word.timestamp + delta).max(0.0),
+            end: word.end.map(|end| (end + delta).max(0.0)),
+            text: word.text.clone(),
+        })
+        .collect()
 }
 
 fn extract_clean_text(content: &str) -> String {
     // 移除所有时间戳标记，保留纯文本
-    let word_timestamp_regex = Regex::new(r"<\d+:\d+\.\d+>").unwrap();
-    word_timestamp_regex
+    WORD_TIMESTAMP_REGEX
         .replace_all(content, "")
         .trim()
         .to_string()
 }
+
+/// 把另一份歌词按时间戳合并为翻译
+///
+/// 两份歌词都按时间顺序对齐，每行翻译最多使用一次。翻译行与原文相差不到
+/// `tolerance` 秒，且离这一行比离下一行更近时才会采用。已有翻译的行保持不变。
+pub(crate) fn merge_translation(main: &mut Lyrics, translation: &Lyrics, tolerance: f64) {
+    let translations = &translation.lines;
+    let mut next = 0;
+    for i in 0..main.lines.len() {
+        let timestamp = main.lines[i].timestamp;
+        let following = main.lines.get(i + 1).map(|line| line.timestamp);
+        // 跳过已经错过的翻译
+        while next < translations.len() && translations[next].timestamp <= timestamp - tolerance {
+            next += 1;
+        }
+        let best = translations[next..]
+            .iter()
+            .enumerate()
+            .take_while(|(_, t)| t.timestamp < timestamp + tolerance)
+            .map(|(offset, t)| (offset, t, (t.timestamp - timestamp).abs()))
+            .filter(|(_, t, distance)| {
+                following.is_none_or(|f| *distance <= (f - t.timestamp).abs())
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+        if let Some((offset, t, _)) = best {
+            next += offset + 1;
+            let line = &mut main.lines[i];
+            if line.translation.is_none() {
+                line.translation = Some(t.text.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "expected {expected}, got {actual}"
+        );
+    }
+
+    #[test]
+    fn timestamp_forms() {
+        assert_close(parse_timestamp("01:02").unwrap(), 62.0);
+        assert_close(parse_timestamp("01:02.34").unwrap(), 62.34);
+        assert_close(parse_timestamp("01:02:34").unwrap(), 62.34);
+        assert_close(parse_timestamp("01:01:02.345").unwrap(), 3662.345);
+    }
+
+    #[test]
+    fn fraction_scales_by_digit_count() {
+        assert_close(parse_timestamp("00:12.5").unwrap(), 12.5);
+        assert_close(parse_timestamp("00:12.34").unwrap(), 12.34);
+        assert_close(parse_timestamp("00:12.034").unwrap(), 12.034);
+    }
+
+    #[test]
+    fn invalid_timestamps() {
+        assert!(parse_timestamp("ti:title").is_none());
+        assert!(parse_timestamp("00:1x").is_none());
+        assert!(parse_timestamp("00:12.").is_none());
+        assert!(parse_timestamp("1:2:3:4").is_none());
+    }
+
+    #[test]
+    fn positive_offset_shows_lyrics_earlier() {
+        let lyrics = parse_lyrics("[offset:+500]\n[00:01.00]one\n[00:02.00]two");
+        assert_eq!(lyrics.metadata.offset, 500);
+        assert_close(lyrics.lines[0].timestamp, 0.5);
+        assert_close(lyrics.lines[1].timestamp, 1.5);
+    }
+
+    #[test]
+    fn negative_offset_shows_lyrics_later() {
+        let lyrics = parse_lyrics("[offset:-500]\n[00:01.00]one");
+        assert_eq!(lyrics.metadata.offset, -500);
+        assert_close(lyrics.lines[0].timestamp, 1.5);
+    }
+
+    #[test]
+    fn id_tags_are_captured() {
+        let lyrics = parse_lyrics(
+            "[ar:Artist]\n[ti: Title ]\n[al:Album]\n[au:Author]\n[by:Maker]\n\
+             [length:03:25]\n[re:Editor]\n[00:01.00]line",
+        );
+        let metadata = &lyrics.metadata;
+        assert_eq!(metadata.artist.as_deref(), Some("Artist"));
+        assert_eq!(metadata.title.as_deref(), Some("Title"));
+        assert_eq!(metadata.album.as_deref(), Some("Album"));
+        assert_eq!(metadata.author.as_deref(), Some("Author"));
+        assert_eq!(metadata.by.as_deref(), Some("Maker"));
+        assert_close(metadata.length.unwrap(), 205.0);
+        assert_eq!(
+            metadata.extra,
+            vec![("re".to_string(), "Editor".to_string())]
+        );
+        assert_eq!(lyrics.lines.len(), 1);
+        assert_eq!(lyrics.lines[0].text, "line");
+    }
+
+    #[test]
+    fn compressed_timestamps_expand_into_sorted_lines() {
+        let lyrics = parse_lyrics("[00:10.00][00:30.00]chorus\n[00:20.00]verse");
+        let lines: Vec<_> = lyrics
+            .lines
+            .iter()
+            .map(|line| (line.timestamp, line.text.as_str()))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![(10.0, "chorus"), (20.0, "verse"), (30.0, "chorus")]
+        );
+    }
+
+    fn translations(lyrics: &Lyrics) -> Vec<Option<&str>> {
+        lyrics
+            .lines
+            .iter()
+            .map(|line| line.translation.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn translation_lines_are_used_once_in_order() {
+        let mut main =
+            parse_lyrics("[00:10.00]one\n[00:10.40]two\n[00:12.00]three\n[00:14.00]four");
+        // 第二行没有翻译
+        let translation = parse_lyrics("[00:10.10]一\n[00:12.20]三\n[00:13.80]四");
+        merge_translation(&mut main, &translation, 1.0);
+        assert_eq!(
+            translations(&main),
+            vec![Some("一"), None, Some("三"), Some("四")]
+        );
+    }
+
+    #[test]
+    fn repeated_lines_take_their_own_translation() {
+        let mut main = parse_lyrics("[00:10.00]la\n[00:10.50]la\n[00:11.00]la");
+        let translation = parse_lyrics("[00:10.00]啦一\n[00:10.50]啦二\n[00:11.00]啦三");
+        merge_translation(&mut main, &translation, 1.0);
+        assert_eq!(
+            translations(&main),
+            vec![Some("啦一"), Some("啦二"), Some("啦三")]
+        );
+    }
+
+    #[test]
+    fn translations_outside_tolerance_are_ignored() {
+        let mut main = parse_lyrics("[00:10.00]one\n[00:20.00]two");
+        let translation = parse_lyrics("[00:15.00]一\n[00:20.00]二");
+        merge_translation(&mut main, &translation, 0.01);
+        assert_eq!(translations(&main), vec![None, Some("二")]);
+    }
+
+    #[test]
+    fn compressed_translation_pairs_with_each_copy() {
+        let lyrics = parse_lyrics("[00:10.00][00:30.00]chorus\n[00:10.00][00:30.00]副歌");
+        assert_eq!(lyrics.lines.len(), 2);
+        for line in &lyrics.lines {
+            assert_eq!(line.translation.as_deref(), Some("副歌"));
+        }
+    }
+
+    #[test]
+    fn compressed_translation_only_for_matching_copy() {
+        let lyrics = parse_lyrics("[00:10.00][00:30.00]chorus\n[00:10.00]副歌\n[00:20.00]verse");
+        let translations: Vec<_> = lyrics
+            .lines
+            .iter()
+            .map(|line| (line.text.as_str(), line.translation.as_deref()))
+            .collect();
+        assert_eq!(
+            translations,
+            vec![("chorus", Some("副歌")), ("verse", None), ("chorus", None)]
+        );
+    }
+
+    #[test]
+    fn compressed_word_timing_shifts_per_copy() {
+        let lyrics = parse_lyrics("[00:10.00][00:30.00]<00:10.00>la <00:10.50>la<00:11.00>");
+        let second = &lyrics.lines[1];
+        let words = second.words.as_ref().unwrap();
+        assert_close(second.timestamp, 30.0);
+        assert_close(words[0].timestamp, 30.0);
+        assert_close(words[1].timestamp, 30.5);
+        assert_close(words[1].end.unwrap(), 31.0);
+    }
+
+    #[test]
+    fn words_split_on_markers_with_end_times() {
+        let (text, words) = extract_words("<00:12.00>Hel<00:12.30>lo <00:12.80>world<00:13.20>");
+        assert_eq!(text, "Hello world");
+        let words = words.unwrap();
+        let spans: Vec<_> = words
+            .iter()
+            .map(|w| (w.text.as_str(), w.timestamp, w.end))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("Hel", 12.0, Some(12.3)),
+                ("lo ", 12.3, Some(12.8)),
+                ("world", 12.8, Some(13.2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn text_before_first_marker_joins_first_word() {
+        let (text, words) = extract_words(" oh <00:01.00>yeah<00:02.00>");
+        assert_eq!(text, "oh yeah");
+        let words = words.unwrap();
+        assert_eq!(words[0].text, "oh yeah");
+        assert_close(words[0].timestamp, 1.0);
+        let joined: String = words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(joined, text);
+    }
+
+    #[test]
+    fn plain_line_has_no_words() {
+        let (text, words) = extract_words("just text");
+        assert_eq!(text, "just text");
+        assert!(words.is_none());
+        let (text, words) = extract_words("<00:01.00>");
+        assert_eq!(text, "");
+        assert!(words.is_none());
+    }
+}
@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+
+use crate::error::Result;
+use crate::models::{Lyrics, SongInfo};
+
+/// 一次歌词查询
+#[derive(Debug, Clone)]
+pub struct LyricsQuery {
+    pub song: SongInfo,
+    /// 本地音频文件，歌曲 URL 不是本地文件时为 `None`
+    pub path: Option<PathBuf>,
+}
+
+/// 歌词来源，例如内嵌标签、外置文件、缓存、在线服务、用户脚本
+///
+/// 没有歌词时返回 [`Error::NoLyrics`](crate::Error::NoLyrics)，其他错误会作为查找失败的原因报告。
+/// 查询在单独的线程中进行，超时后结果被丢弃，但线程不会被中断：
+/// 来源需要自己在期限内结束，例如脚本来源超时后结束子进程，在线来源的请求也有超时。
+pub trait LyricsProvider: Send + Sync {
+    /// 来源名称，用于日志和 `timeouts` 配置
+    fn name(&self) -> &str;
+
+    fn fetch(&self, query: &LyricsQuery) -> Result<Lyrics>;
+
+    /// 结果是否写入本地缓存，远程和脚本来源应返回 `true`
+    fn cacheable(&self) -> bool {
+        false
+    }
+}
+
+/// 在线结果的时长（秒）与歌曲相差不超过 `tolerance` 时视为同一首，任一方没有时长时不作判断
+pub(crate) fn duration_matches(song: &SongInfo, duration: Option<f64>, tolerance: f64) -> bool {
+    match (song.length, duration) {
+        (Some(length), Some(duration)) => (length - duration).abs() <= tolerance,
+        _ => true,
+    }
+}
@@ -0,0 +1,154 @@
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::error::{Error, Result};
+use crate::lyrics::charset::decode_lyrics_bytes;
+use crate::lyrics::parser::parse_lyrics;
+use crate::lyrics::provider::{LyricsProvider, LyricsQuery};
+use crate::models::Lyrics;
+
+/// 等待脚本退出时检查的间隔
+const WAIT_INTERVAL: Duration = Duration::from_millis(10);
+
+/// 运行用户脚本获取歌词
+///
+/// 歌曲信息通过 `BARD_*` 环境变量传入，标准输出为 LRC 文本。超时后结束脚本进程。
+pub struct ScriptProvider {
+    command: String,
+    timeout: Duration,
+}
+
+impl ScriptProvider {
+    pub fn new(command: String, timeout: Duration) -> Self {
+        Self { command, timeout }
+    }
+}
+
+impl LyricsProvider for ScriptProvider {
+    fn name(&self) -> &str {
+        "script"
+    }
+
+    fn fetch(&self, query: &LyricsQuery) -> Result<Lyrics> {
+        if self.command.trim().is_empty() {
+            return Err(Error::provider(
+                self.name(),
+                "lyrics.script.command is not set",
+            ));
+        }
+        let song = &query.song;
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(&self.command)
+            .env("BARD_ARTIST", &song.artist)
+            .env("BARD_TITLE", &song.title)
+            .env("BARD_ALBUM", song.album.as_deref().unwrap_or_default())
+            .env(
+                "BARD_LENGTH",
+                song.length.map(|l| format!("{:.3}", l)).unwrap_or_default(),
+            )
+            .env("BARD_URL", song.url.as_deref().unwrap_or_default())
+            .env("BARD_PATH", query.path.as_deref().unwrap_or("".as_ref()));
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| Error::provider(self.name(), format!("{}: {}", self.command, e)))?;
+        // 在单独的线程中读取输出，避免管道写满后脚本阻塞
+        let stdout = read_to_end(child.stdout.take());
+        let stderr = read_to_end(child.stderr.take());
+
+        let deadline = Instant::now() + self.timeout;
+        let status = loop {
+            match child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if Instant::now() < deadline => thread::sleep(WAIT_INTERVAL),
+                Ok(None) => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(Error::Timeout(self.name().to_string()));
+                }
+                Err(e) => {
+                    return Err(Error::provider(
+                        self.name(),
+                        format!("{}: {}", self.command, e),
+                    ));
+                }
+            }
+        };
+        let stdout = stdout.join().unwrap_or_default();
+        if !status.success() {
+            let stderr = stderr.join().unwrap_or_default();
+            let stderr = String::from_utf8_lossy(&stderr);
+            return Err(Error::provider(
+                self.name(),
+                format!(
+                    "{} exited with {}: {}",
+                    self.command,
+                    status,
+                    stderr.lines().next().unwrap_or_default()
+                ),
+            ));
+        }
+
+        Some(parse_lyrics(&decode_lyrics_bytes(&stdout, None)))
+            .filter(|lyrics| !lyrics.lines.is_empty())
+            .ok_or_else(|| Error::NoLyrics(song.id.clone()))
+    }
+
+    fn cacheable(&self) -> bool {
+        true
+    }
+}
+
+fn read_to_end(pipe: Option<impl Read + Send + 'static>) -> JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        bytes
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::song;
+
+    fn query() -> LyricsQuery {
+        LyricsQuery {
+            song: song("Artist", "Title"),
+            path: None,
+        }
+    }
+
+    #[test]
+    fn script_output_is_parsed() {
+        let command = r#"printf '[00:01.00]%s - %s\n' "$BARD_ARTIST" "$BARD_TITLE""#;
+        let provider = ScriptProvider::new(command.to_string(), Duration::from_secs(5));
+        let lyrics = provider.fetch(&query()).unwrap();
+        assert_eq!(lyrics.lines[0].text, "Artist - Title");
+        assert_eq!(lyrics.lines[0].timestamp, 1.0);
+    }
+
+    #[test]
+    fn timed_out_script_is_killed() {
+        let pid_file = std::env::temp_dir().join(format!("bard-script-{}.pid", std::process::id()));
+        let command = format!("echo $$ > {}; sleep 10", pid_file.display());
+        let provider = ScriptProvider::new(command, Duration::from_millis(200));
+
+        let start = Instant::now();
+        let result = provider.fetch(&query());
+        assert!(matches!(result, Err(Error::Timeout(_))));
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let _ = std::fs::remove_file(&pid_file);
+        assert!(!std::path::Path::new("/proc").join(pid.trim()).exists());
+    }
+}
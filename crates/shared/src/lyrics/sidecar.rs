@@ -0,0 +1,150 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use crate::config::{LyricsConfig, expand_home};
+use crate::models::SongInfo;
+
+/// 按配置的模板顺序查找外置歌词文件
+pub fn find_sidecar(
+    song: &SongInfo,
+    music_path: Option<&Path>,
+    config: &LyricsConfig,
+) -> Option<PathBuf> {
+    config
+        .sidecar_templates
+        .iter()
+        .filter_map(|template| expand_template(template, song, music_path))
+        .find_map(|candidate| resolve_case_insensitive(&candidate))
+}
+
+/// 展开路径模板；模板需要音频路径或专辑名但歌曲没有时返回 `None`
+fn expand_template(template: &str, song: &SongInfo, music_path: Option<&Path>) -> Option<PathBuf> {
+    let mut expanded = template.to_string();
+    if template.contains("{dir}") || template.contains("{stem}") {
+        let music_path = music_path?;
+        let dir = music_path.parent()?.to_str()?;
+        let stem = music_path.file_stem()?.to_str()?;
+        expanded = expanded.replace("{dir}", dir).replace("{stem}", stem);
+    }
+    if template.contains("{album}") {
+        expanded = expanded.replace("{album}", &song.album.as_ref()?.replace('/', "_"));
+    }
+    // 标签中的 `/` 不能出现在文件名里
+    expanded = expanded
+        .replace("{artist}", &song.artist.replace('/', "_"))
+        .replace("{title}", &song.title.replace('/', "_"));
+    Some(expand_home(&expanded))
+}
+
+/// 文件存在时直接返回，否则在同目录下查找仅扩展名大小写不同的文件，例如 `Song.LRC`
+fn resolve_case_insensitive(candidate: &Path) -> Option<PathBuf> {
+    if candidate.is_file() {
+        return Some(candidate.to_path_buf());
+    }
+    let stem = candidate.file_stem()?;
+    let extension = candidate.extension().and_then(OsStr::to_str)?;
+    std::fs::read_dir(candidate.parent()?)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .find(|path| {
+            path.file_stem() == Some(stem)
+                && path
+                    .extension()
+                    .and_then(OsStr::to_str)
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+                && path.is_file()
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{song, temp_path};
+
+    fn tagged_song() -> SongInfo {
+        SongInfo {
+            album: Some("Best/Of".to_string()),
+            ..song("AC/DC", "Back/Track")
+        }
+    }
+
+    #[test]
+    fn placeholders_are_expanded() {
+        let music = Path::new("/music/Album/01 Song.flac");
+        let expand = |template| expand_template(template, &tagged_song(), Some(music));
+        assert_eq!(
+            expand("{dir}/{stem}.lrc"),
+            Some(PathBuf::from("/music/Album/01 Song.lrc"))
+        );
+        // 标签中的 `/` 替换为 `_`
+        assert_eq!(
+            expand("/lyrics/{album}/{artist} - {title}.lrc"),
+            Some(PathBuf::from("/lyrics/Best_Of/AC_DC - Back_Track.lrc"))
+        );
+    }
+
+    #[test]
+    fn missing_path_or_album_skips_template() {
+        let song = song("Artist", "Title");
+        assert_eq!(expand_template("{dir}/{stem}.lrc", &song, None), None);
+        assert_eq!(
+            expand_template("/lyrics/{album}.lrc", &song, Some(Path::new("/a.flac"))),
+            None
+        );
+        assert_eq!(
+            expand_template("/lyrics/{artist}.lrc", &song, None),
+            Some(PathBuf::from("/lyrics/Artist.lrc"))
+        );
+    }
+
+    #[test]
+    fn home_is_expanded() {
+        let Some(home) = std::env::var_os("HOME") else {
+            return;
+        };
+        let expanded = expand_template("~/Lyrics/{title}.lrc", &song("A", "T"), None);
+        assert_eq!(expanded, Some(PathBuf::from(home).join("Lyrics/T.lrc")));
+    }
+
+    #[test]
+    fn extension_case_is_ignored() {
+        let dir = temp_path("sidecar");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Song.LRC"), "").unwrap();
+        std::fs::write(dir.join("Other.lrc"), "").unwrap();
+        std::fs::create_dir(dir.join("Folder.lrc")).unwrap();
+
+        let found = resolve_case_insensitive(&dir.join("Song.lrc"));
+        let exact = resolve_case_insensitive(&dir.join("Other.lrc"));
+        let stem_differs = resolve_case_insensitive(&dir.join("song.lrc"));
+        let directory = resolve_case_insensitive(&dir.join("Folder.LRC"));
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(found, Some(dir.join("Song.LRC")));
+        assert_eq!(exact, Some(dir.join("Other.lrc")));
+        // 只忽略扩展名的大小写
+        assert_eq!(stem_differs, None);
+        assert_eq!(directory, None);
+    }
+
+    #[test]
+    fn templates_are_tried_in_order() {
+        let dir = temp_path("sidecar-order");
+        let _ = std::fs::remove_dir_all(dir.join("Lyrics"));
+        std::fs::create_dir_all(dir.join("Lyrics")).unwrap();
+        std::fs::write(dir.join("Lyrics").join("Song.lrc"), "").unwrap();
+
+        let music = dir.join("Song.flac");
+        let config = LyricsConfig {
+            sidecar_templates: vec![
+                "{dir}/{stem}.lrc".to_string(),
+                "{dir}/Lyrics/{stem}.lrc".to_string(),
+            ],
+            ..LyricsConfig::default()
+        };
+        let found = find_sidecar(&song("A", "T"), Some(&music), &config);
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(found, Some(dir.join("Lyrics").join("Song.lrc")));
+    }
+}
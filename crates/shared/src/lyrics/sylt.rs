@@ -0,0 +1,343 @@
+use std::fs::File;
+use std::path::Path;
+
+use encoding_rs::Encoding;
+use lofty::config::ParseOptions;
+use lofty::file::{AudioFile, FileType};
+use lofty::id3::v2::{
+    Frame, FrameId, Id3v2Tag, SyncTextContentType, SynchronizedTextFrame, TimestampFormat,
+};
+use lofty::mpeg::{Layer, MpegFile, MpegVersion};
+use lofty::probe::Probe;
+
+use crate::lyrics::charset::repair_tag_text;
+use crate::lyrics::embedded::language_code;
+use crate::models::{LyricLine, LyricWord, Lyrics};
+
+/// 读取 ID3v2 SYLT 帧中的同步歌词，每种语言一份
+///
+/// MP3 之外，AAC/AIFF/WAV 中的 ID3v2 标签也会被读取（仅支持毫秒时间戳）。
+pub fn read_sylt_lyrics(music_path: &Path, forced: Option<&'static Encoding>) -> Vec<Lyrics> {
+    read_sylt_frames(music_path, forced).unwrap_or_default()
+}
+
+fn read_sylt_frames(music_path: &Path, forced: Option<&'static Encoding>) -> Option<Vec<Lyrics>> {
+    let file_type = Probe::open(music_path)
+        .ok()?
+        .guess_file_type()
+        .ok()?
+        .file_type()?;
+    let mut file = File::open(music_path).ok()?;
+    let options = ParseOptions::new();
+
+    let (tag, seconds_per_frame) = match file_type {
+        FileType::Mpeg => {
+            let mpeg = MpegFile::read_from(&mut file, options).ok()?;
+            let properties = mpeg.properties();
+            let seconds_per_frame = match properties.sample_rate() {
+                0 => None,
+                rate => {
+                    Some(samples_per_frame(properties.version(), properties.layer()) / rate as f64)
+                }
+            };
+            (mpeg.id3v2().cloned(), seconds_per_frame)
+        }
+        FileType::Aac => (
+            lofty::aac::AacFile::read_from(&mut file, options)
+                .ok()?
+                .id3v2()
+                .cloned(),
+            None,
+        ),
+        FileType::Aiff => (
+            lofty::iff::aiff::AiffFile::read_from(&mut file, options)
+                .ok()?
+                .id3v2()
+                .cloned(),
+            None,
+        ),
+        FileType::Wav => (
+            lofty::iff::wav::WavFile::read_from(&mut file, options)
+                .ok()?
+                .id3v2()
+                .cloned(),
+            None,
+        ),
+        _ => return None,
+    };
+
+    let lyrics = best_sylt_frames(&tag?)
+        .iter()
+        .filter_map(|frame| {
+            let lines = sylt_to_lines(frame, seconds_per_frame, forced)?;
+            let mut lyrics = Lyrics {
+                lines,
+                ..Default::default()
+            };
+            lyrics.metadata.language = language_code(&frame.language);
+            Some(lyrics)
+        })
+        .collect();
+    Some(lyrics)
+}
+
+/// 按内容类型选出最合适的 SYLT 帧（歌词 > 文字转录 > 其他），同类型的多种语言都保留
+fn best_sylt_frames(tag: &Id3v2Tag) -> Vec<SynchronizedTextFrame<'static>> {
+    let rank = |content_type: SyncTextContentType| match content_type {
+        SyncTextContentType::Lyrics => Some(0),
+        SyncTextContentType::TextTranscription => Some(1),
+        SyncTextContentType::Other => Some(2),
+        _ => None,
+    };
+    let frames: Vec<_> = tag
+        .into_iter()
+        .filter_map(|frame| match frame {
+            Frame::Binary(binary) if *binary.id() == FrameId::Valid("SYLT".into()) => {
+                SynchronizedTextFrame::parse(&binary.data, binary.flags()).ok()
+            }
+            _ => None,
+        })
+        .filter(|frame| !frame.content.is_empty())
+        .filter_map(|frame| rank(frame.content_type).map(|r| (r, frame)))
+        .collect();
+    let Some(best) = frames.iter().map(|(r, _)| *r).min() else {
+        return Vec::new();
+    };
+    frames
+        .into_iter()
+        .filter(|(r, _)| *r == best)
+        .map(|(_, frame)| frame)
+        .collect()
+}
+
+/// 将 SYLT 内容转换为歌词行
+///
+/// 条目以换行开头时表示按音节同步：换行开始新的一行，其余条目作为逐字时间；
+/// 否则每个条目就是一行。
+fn sylt_to_lines(
+    frame: &SynchronizedTextFrame<'_>,
+    seconds_per_frame: Option<f64>,
+    forced: Option<&'static Encoding>,
+) -> Option<Vec<LyricLine>> {
+    let to_seconds = |timestamp: u32| match frame.timestamp_format {
+        TimestampFormat::MS => Some(timestamp as f64 / 1000.0),
+        TimestampFormat::MPEG => seconds_per_frame.map(|s| timestamp as f64 * s),
+    };
+    let entries: Vec<(f64, String)> = frame
+        .content
+        .iter()
+        .map(|(timestamp, text)| Some((to_seconds(*timestamp)?, repair_tag_text(text, forced))))
+        .collect::<Option<_>>()?;
+
+    let syllables = entries
+        .iter()
+        .skip(1)
+        .any(|(_, text)| text.starts_with(['\n', '\r']));
+
+    let mut lines: Vec<LyricLine> = Vec::new();
+    if syllables {
+        for (timestamp, text) in entries {
+            let starts_line = text.starts_with(['\n', '\r']) || lines.is_empty();
+            let text = text.trim_start_matches(['\n', '\r']).to_string();
+            if starts_line {
+                lines.push(LyricLine {
+                    timestamp,
+                    ..Default::default()
+                });
+            }
+            let line = lines.last_mut()?;
+            line.text.push_str(&text);
+            line.words.get_or_insert_with(Vec::new).push(LyricWord {
+                timestamp,
+                end: None,
+                text,
+            });
+        }
+        // 去掉首尾空白，保证逐字拼接结果与整行文本一致
+        for line in &mut lines {
+            line.text = line.text.trim().to_string();
+            if let Some(words) = &mut line.words {
+                if let Some(first) = words.first_mut() {
+                    first.text = first.text.trim_start().to_string();
+                }
+                if let Some(last) = words.last_mut() {
+                    last.text = last.text.trim_end().to_string();
+                }
+                words.retain(|word| !word.text.is_empty());
+            }
+        }
+    } else {
+        lines = entries
+            .into_iter()
+            .map(|(timestamp, text)| LyricLine {
+                timestamp,
+                text: text.trim().to_string(),
+                ..Default::default()
+            })
+            .collect();
+    }
+
+    lines.retain(|line| !line.text.is_empty());
+    lines.sort_by(|a, b| a.timestamp.total_cmp(&b.timestamp));
+    (!lines.is_empty()).then_some(lines)
+}
+
+fn samples_per_frame(version: &MpegVersion, layer: &Layer) -> f64 {
+    match (layer, version) {
+        (Layer::Layer1, _) => 384.0,
+        (Layer::Layer2, _) | (Layer::Layer3, MpegVersion::V1) => 1152.0,
+        (Layer::Layer3, _) => 576.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lofty::TextEncoding;
+    use lofty::id3::v2::BinaryFrame;
+
+    fn frame(
+        content_type: SyncTextContentType,
+        language: &[u8; 3],
+        timestamp_format: TimestampFormat,
+        content: &[(u32, &str)],
+    ) -> SynchronizedTextFrame<'static> {
+        SynchronizedTextFrame::new(
+            TextEncoding::UTF8,
+            *language,
+            timestamp_format,
+            content_type,
+            None,
+            content.iter().map(|(t, s)| (*t, s.to_string())).collect(),
+        )
+    }
+
+    fn lyrics_frame(content: &[(u32, &str)]) -> SynchronizedTextFrame<'static> {
+        frame(
+            SyncTextContentType::Lyrics,
+            b"eng",
+            TimestampFormat::MS,
+            content,
+        )
+    }
+
+    /// 与读取文件时一样，SYLT 帧以未解析的二进制帧存放在标签中
+    fn tag_with(frames: &[SynchronizedTextFrame<'static>]) -> Id3v2Tag {
+        let mut tag = Id3v2Tag::new();
+        for frame in frames {
+            tag.insert(Frame::Binary(BinaryFrame::new(
+                FrameId::Valid("SYLT".into()),
+                frame.as_bytes().unwrap(),
+            )));
+        }
+        tag
+    }
+
+    fn texts(lines: &[LyricLine]) -> Vec<(f64, &str)> {
+        lines
+            .iter()
+            .map(|line| (line.timestamp, line.text.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn each_entry_is_a_line() {
+        let frame = lyrics_frame(&[(2500, " second "), (1000, "first"), (3000, "  ")]);
+        let lines = sylt_to_lines(&frame, None, None).unwrap();
+        assert_eq!(texts(&lines), vec![(1.0, "first"), (2.5, "second")]);
+        assert!(lines.iter().all(|line| line.words.is_none()));
+    }
+
+    #[test]
+    fn newlines_mark_syllable_lines() {
+        let frame = lyrics_frame(&[
+            (1000, "Hel"),
+            (1300, "lo "),
+            (1600, "\nworld "),
+            (2000, "\ragain"),
+        ]);
+        let lines = sylt_to_lines(&frame, None, None).unwrap();
+        assert_eq!(
+            texts(&lines),
+            vec![(1.0, "Hello"), (1.6, "world"), (2.0, "again")]
+        );
+        let words: Vec<_> = lines[0]
+            .words
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|word| (word.timestamp, word.text.as_str()))
+            .collect();
+        assert_eq!(words, vec![(1.0, "Hel"), (1.3, "lo")]);
+        assert_eq!(lines[1].words.as_ref().unwrap()[0].text, "world");
+    }
+
+    #[test]
+    fn mpeg_frames_need_frame_duration() {
+        let frame = frame(
+            SyncTextContentType::Lyrics,
+            b"eng",
+            TimestampFormat::MPEG,
+            &[(100, "line")],
+        );
+        assert!(sylt_to_lines(&frame, None, None).is_none());
+
+        let seconds_per_frame = samples_per_frame(&MpegVersion::V1, &Layer::Layer3) / 44100.0;
+        let lines = sylt_to_lines(&frame, Some(seconds_per_frame), None).unwrap();
+        assert!((lines[0].timestamp - 100.0 * 1152.0 / 44100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn samples_per_frame_by_layer_and_version() {
+        assert_eq!(samples_per_frame(&MpegVersion::V1, &Layer::Layer1), 384.0);
+        assert_eq!(samples_per_frame(&MpegVersion::V2, &Layer::Layer2), 1152.0);
+        assert_eq!(samples_per_frame(&MpegVersion::V1, &Layer::Layer3), 1152.0);
+        assert_eq!(samples_per_frame(&MpegVersion::V2, &Layer::Layer3), 576.0);
+        assert_eq!(samples_per_frame(&MpegVersion::V2_5, &Layer::Layer3), 576.0);
+    }
+
+    #[test]
+    fn lyrics_frames_beat_other_content() {
+        let tag = tag_with(&[
+            frame(
+                SyncTextContentType::TextTranscription,
+                b"eng",
+                TimestampFormat::MS,
+                &[(0, "transcript")],
+            ),
+            lyrics_frame(&[(0, "english")]),
+            frame(
+                SyncTextContentType::Lyrics,
+                b"jpn",
+                TimestampFormat::MS,
+                &[(0, "japanese")],
+            ),
+            frame(
+                SyncTextContentType::Chord,
+                b"eng",
+                TimestampFormat::MS,
+                &[(0, "Am")],
+            ),
+        ]);
+        let frames = best_sylt_frames(&tag);
+        let languages: Vec<_> = frames.iter().map(|frame| &frame.language).collect();
+        assert_eq!(languages, [b"eng", b"jpn"]);
+    }
+
+    #[test]
+    fn empty_frames_are_skipped() {
+        let tag = tag_with(&[
+            lyrics_frame(&[]),
+            frame(
+                SyncTextContentType::Other,
+                b"eng",
+                TimestampFormat::MS,
+                &[(0, "other")],
+            ),
+        ]);
+        let frames = best_sylt_frames(&tag);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].content_type, SyncTextContentType::Other);
+        assert!(best_sylt_frames(&tag_with(&[])).is_empty());
+    }
+}
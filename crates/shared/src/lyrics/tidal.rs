@@ -0,0 +1,256 @@
+use std::sync::Mutex;
+
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, Response};
+use serde::Deserialize;
+
+use crate::config::TidalLyricsConfig;
+use crate::error::{Error, Result};
+use crate::http;
+use crate::lyrics::parser::parse_lyrics;
+use crate::lyrics::provider::{LyricsProvider, LyricsQuery};
+use crate::models::{Lyrics, SongInfo};
+use crate::tidal::{TidalAuth, TidalTokens};
+
+/// Tidal 在线歌词，只查询 Tidal 播放的曲目
+///
+/// 曲目 id 来自 tidal-hifi 的 MPRIS 元数据，令牌来自 `bard tidal login` 写入的令牌文件。
+pub struct TidalProvider {
+    client: Client,
+    config: TidalLyricsConfig,
+    /// 第一次查询时从令牌文件读取，之后登录的也能用上
+    auth: Mutex<Option<TidalAuth>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LyricsResponse {
+    /// 纯文本歌词
+    lyrics: Option<String>,
+    /// LRC 格式的同步歌词
+    subtitles: Option<String>,
+}
+
+impl TidalProvider {
+    pub fn new(config: &TidalLyricsConfig) -> Self {
+        Self {
+            client: http::client(),
+            config: config.clone(),
+            auth: Mutex::new(None),
+        }
+    }
+
+    fn request(&self, track_id: u64, token: &str) -> Result<Response> {
+        self.client
+            .get(format!(
+                "{}/v1/tracks/{}/lyrics",
+                self.config.api_url.trim_end_matches('/'),
+                track_id
+            ))
+            .query(&[("countryCode", self.config.country_code.as_str())])
+            .bearer_auth(token)
+            .send()
+            .map_err(|e| Error::provider(self.name(), e))
+    }
+
+    /// 当前的登录状态，第一次调用时读取令牌文件
+    fn load_auth(&self) -> Result<TidalAuth> {
+        let mut guard = self.auth.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(auth) = guard.as_ref() {
+            return Ok(auth.clone());
+        }
+        let path = self
+            .config
+            .token_path()
+            .map_err(|e| Error::provider(self.name(), format!("{:#}", e)))?;
+        let tokens = TidalTokens::load(&path)
+            .map_err(|e| Error::provider(self.name(), format!("{:#}", e)))?
+            .ok_or_else(|| Error::provider(self.name(), "not logged in, run `bard tidal login`"))?;
+        Ok(guard
+            .insert(TidalAuth::new(&self.config.auth_url, tokens, path))
+            .clone())
+    }
+
+    /// 带令牌请求歌词，令牌过期或被拒绝时刷新
+    fn authorized_request(&self, auth: &mut TidalAuth, track_id: u64) -> Result<Response> {
+        let token = auth
+            .get_valid_access_token()
+            .map_err(|e| Error::provider(self.name(), format!("{:#}", e)))?;
+        let response = self.request(track_id, &token)?;
+        // 令牌可能在过期前被吊销，刷新后重试一次
+        if response.status() != StatusCode::UNAUTHORIZED {
+            return Ok(response);
+        }
+        let token = auth
+            .refresh_access_token()
+            .map_err(|e| Error::provider(self.name(), format!("{:#}", e)))?;
+        self.request(track_id, &token)
+    }
+}
+
+impl LyricsProvider for TidalProvider {
+    fn name(&self) -> &str {
+        "tidal"
+    }
+
+    fn fetch(&self, query: &LyricsQuery) -> Result<Lyrics> {
+        let no_lyrics = || Error::NoLyrics(query.song.id.clone());
+        let Some(track_id) = tidal_track_id(&query.song) else {
+            return Err(no_lyrics());
+        };
+
+        // 请求期间不持有锁，只在令牌刷新后写回
+        let mut auth = self.load_auth()?;
+        let access_token = auth.tokens().access_token.clone();
+        let response = self.authorized_request(&mut auth, track_id);
+        if auth.tokens().access_token != access_token {
+            *self.auth.lock().unwrap_or_else(|e| e.into_inner()) = Some(auth);
+        }
+        let response = response?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(no_lyrics());
+        }
+        let response: LyricsResponse = response
+            .error_for_status()
+            .and_then(|response| response.json())
+            .map_err(|e| Error::provider(self.name(), e))?;
+
+        let text = [response.subtitles, response.lyrics]
+            .into_iter()
+            .flatten()
+            .find(|text| !text.trim().is_empty())
+            .ok_or_else(no_lyrics)?;
+        Some(parse_lyrics(&text))
+            .filter(|lyrics| !lyrics.lines.is_empty())
+            .ok_or_else(no_lyrics)
+    }
+
+    fn cacheable(&self) -> bool {
+        true
+    }
+}
+
+/// Tidal 曲目 id
+///
+/// tidal-hifi 的 `xesam:url` 形如 `https://tidal.com/browse/track/<id>`，
+/// `mpris:trackid` 的最后一段也是曲目 id。
+pub fn tidal_track_id(song: &SongInfo) -> Option<u64> {
+    let from_url = song
+        .url
+        .as_deref()
+        .filter(|url| url.contains("tidal.com"))
+        .and_then(|url| url.split_once("/track/"))
+        .and_then(|(_, rest)| rest.split(['/', '?', '#']).next())
+        .and_then(|id| id.parse().ok());
+    from_url.or_else(|| {
+        if !song.player.to_lowercase().contains("tidal") {
+            return None;
+        }
+        song.track_id.as_deref()?.rsplit('/').next()?.parse().ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TestServer, song, temp_path};
+    use serde_json::json;
+
+    fn tidal_song() -> SongInfo {
+        let mut song = song("Artist", "Title");
+        song.player = "tidal-hifi".to_string();
+        song.url = Some("https://tidal.com/browse/track/123".to_string());
+        song
+    }
+
+    #[test]
+    fn track_id_forms() {
+        assert_eq!(tidal_track_id(&tidal_song()), Some(123));
+
+        let mut song = tidal_song();
+        song.url = Some("https://tidal.com/browse/track/456?u".to_string());
+        assert_eq!(tidal_track_id(&song), Some(456));
+
+        song.url = None;
+        song.track_id = Some("/org/mpris/MediaPlayer2/tidal/789".to_string());
+        assert_eq!(tidal_track_id(&song), Some(789));
+
+        // 其他播放器的 trackid 不是 Tidal 的曲目 id
+        song.player = "spotify".to_string();
+        assert_eq!(tidal_track_id(&song), None);
+    }
+
+    #[test]
+    fn unauthorized_refreshes_and_retries() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/v1/oauth2/token" => (
+                200,
+                json!({ "access_token": "new", "expires_in": 3600, "token_type": "Bearer" })
+                    .to_string(),
+            ),
+            "/v1/tracks/123/lyrics" if request.header("authorization") == Some("Bearer new") => (
+                200,
+                json!({ "lyrics": "line", "subtitles": "[00:01.00]line" }).to_string(),
+            ),
+            _ => (401, String::new()),
+        });
+        let path = temp_path("tidal-provider.toml");
+        let tokens = TidalTokens {
+            access_token: "revoked".to_string(),
+            refresh_token: "refresh".to_string(),
+            expires_at: 0,
+        };
+        tokens.save(&path).unwrap();
+        let config = TidalLyricsConfig {
+            auth_url: server.url.clone(),
+            api_url: server.url.clone(),
+            token_file: Some(path.to_string_lossy().into_owned()),
+            ..TidalLyricsConfig::default()
+        };
+
+        let query = LyricsQuery {
+            song: tidal_song(),
+            path: None,
+        };
+        let provider = TidalProvider::new(&config);
+        let lyrics = provider.fetch(&query).unwrap();
+        // 刷新后的令牌留给后续请求
+        provider.fetch(&query).unwrap();
+        let saved = TidalTokens::load(&path).unwrap().unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(lyrics.is_synced());
+        assert_eq!(lyrics.lines[0].text, "line");
+        assert_eq!(saved.access_token, "new");
+
+        let requests = server.requests();
+        assert_eq!(
+            server.paths(),
+            [
+                "/v1/tracks/123/lyrics",
+                "/v1/oauth2/token",
+                "/v1/tracks/123/lyrics",
+                "/v1/tracks/123/lyrics"
+            ]
+        );
+        assert_eq!(requests[0].query("countryCode"), Some("US"));
+        assert_eq!(requests[0].header("authorization"), Some("Bearer revoked"));
+        assert!(
+            requests
+                .iter()
+                .all(|r| r.header("user-agent").unwrap().starts_with("bard/"))
+        );
+    }
+
+    #[test]
+    fn not_logged_in() {
+        let config = TidalLyricsConfig {
+            token_file: Some(temp_path("tidal-none.toml").to_string_lossy().into_owned()),
+            ..TidalLyricsConfig::default()
+        };
+        let query = LyricsQuery {
+            song: tidal_song(),
+            path: None,
+        };
+        let error = TidalProvider::new(&config).fetch(&query).unwrap_err();
+        assert!(error.to_string().contains("bard tidal login"), "{}", error);
+    }
+}
@@ -1,10 +1,58 @@
 use std::fmt::Display;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct LyricLine {
     pub timestamp: f64,
     pub text: String,
     pub translation: Option<String>,
+    /// 逐字时间（增强 LRC `<mm:ss.xx>`），拼接后等于 `text`
+    pub words: Option<Vec<LyricWord>>,
+}
+
+/// 逐字歌词中的一个字/词
+#[derive(Debug, Clone)]
+pub struct LyricWord {
+    pub timestamp: f64,
+    /// 结束时间，未知时为 `None`（持续到下一个字或下一行）
+    pub end: Option<f64>,
+    pub text: String,
+}
+
+/// LRC 文件中的 ID 标签，例如 `[ar:]`、`[ti:]`、`[offset:]`
+#[derive(Debug, Clone, Default)]
+pub struct LyricsMetadata {
+    /// `[ar:]` 艺术家
+    pub artist: Option<String>,
+    /// `[ti:]` 标题
+    pub title: Option<String>,
+    /// `[al:]` 专辑
+    pub album: Option<String>,
+    /// `[au:]` 作词/作曲
+    pub author: Option<String>,
+    /// `[by:]` LRC 文件作者
+    pub by: Option<String>,
+    /// `[length:]` 歌曲长度（秒）
+    pub length: Option<f64>,
+    /// `[offset:]` 全局偏移（毫秒），已应用到所有时间戳
+    pub offset: i64,
+    /// 其他未识别的标签
+    pub extra: Vec<(String, String)>,
+    /// 歌词语言（ISO-639-2，小写），来自 USLT/SYLT 等标签
+    pub language: Option<String>,
+}
+
+/// 解析后的歌词文档
+#[derive(Debug, Clone, Default)]
+pub struct Lyrics {
+    pub metadata: LyricsMetadata,
+    pub lines: Vec<LyricLine>,
+}
+
+impl Lyrics {
+    /// 是否带有时间轴（纯文本歌词的时间戳都是 0）
+    pub fn is_synced(&self) -> bool {
+        self.lines.iter().any(|line| line.timestamp > 0.0)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -12,6 +60,14 @@ pub struct LyricsStatus {
     pub current_line: LyricLine,
     pub next_line: String,
     pub next_timestamp: Option<f64>,
+    /// 当前正在唱的字在 `current_line.words` 中的下标
+    pub current_word: Option<usize>,
+    /// 当前字的进度，0.0 ~ 1.0
+    pub word_progress: f64,
+    /// 下一个字的开始时间
+    pub next_word_timestamp: Option<f64>,
+    /// 当前行的进度，0.0 ~ 1.0
+    pub line_progress: f64,
 }
 impl Display for LyricLine {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -1,5 +1,5 @@
 pub mod lyrics;
 pub mod song;
 
-pub use lyrics::{LyricLine, LyricsStatus};
+pub use lyrics::{LyricLine, LyricWord, Lyrics, LyricsMetadata, LyricsStatus};
 pub use song::{SongInfo, SongStatus};
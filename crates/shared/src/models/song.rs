@@ -1,14 +1,34 @@
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum SongStatus {
     Paused,
     Playing,
+    Stopped,
+    /// 播放器在运行但没有加载曲目（没有标题、地址和 trackid）
+    NoTrack,
 }
 
+#[derive(Debug, Clone)]
 pub struct SongInfo {
+    /// 区分曲目的标识，优先取自 `mpris:trackid` 和 `xesam:url`
     pub id: String,
     pub artist: String,
     pub title: String,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    /// 时长（秒），来自 `mpris:length`
+    pub length: Option<f64>,
+    /// `mpris:trackid`，没有曲目时为 `None`
+    pub track_id: Option<String>,
+    pub track_number: Option<i32>,
+    /// 封面地址（`mpris:artUrl`）
+    pub art_url: Option<String>,
     pub position: f64,
+    /// 播放速率（MPRIS `Rate`），1.0 为正常速度
+    pub rate: f64,
     pub status: SongStatus,
     pub url: Option<String>,
+    /// 播放器的 MPRIS 名称（去掉 `org.mpris.MediaPlayer2.` 前缀），例如 `mpd`
+    pub player: String,
+    /// 播放器的显示名称（MPRIS `Identity`），例如 `Music Player Daemon`
+    pub identity: String,
 }
@@ -1,79 +1,114 @@
+use crate::error::{Error, Result};
 use crate::models::song::{SongInfo, SongStatus};
-use anyhow::{Context, Result};
-use mpris::PlayerFinder;
-fn get_songinfo() -> Result<SongInfo> {
-    let player_finder = PlayerFinder::new().context("Could not connect to D-Bus")?;
+use crate::player::select::player_name;
+use crate::player::{MprisSource, PlayerSource};
+use mpris::{Metadata, MetadataValue, Player};
 
-    let player = player_finder
-        .find_active()
-        .context("Could not find any player")?;
+/// 没有曲目时 `mpris:trackid` 的取值
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
 
-    let status = player
-        .get_playback_status()
-        .context("Could not get playback status")?;
+/// 按默认规则获取当前 MPRIS 播放器的歌曲信息，没有播放器时返回 [`Error::NoPlayer`]
+///
+/// 其他来源见 [`PlayerSource`](crate::player::PlayerSource)。
+pub fn get_current_song() -> Result<SongInfo> {
+    MprisSource::default().snapshot().song
+}
+
+/// 读取播放器的状态和元数据
+pub(crate) fn song_from_player(player: &Player) -> Result<SongInfo> {
+    let status = player.get_playback_status()?;
     let metadata = player
         .get_metadata()
-        .context("Could not get metadata for player")?;
-    let artists = metadata.get("xesam:artist").and_then(|_as| match _as {
-        mpris::MetadataValue::String(a) => Some(vec![a.to_owned()]),
-        mpris::MetadataValue::Array(values) => {
-            let v = values
-                .iter()
-                .filter_map(|s| match s {
-                    mpris::MetadataValue::String(ss) => Some(ss.to_owned()),
-                    _ => None,
-                })
-                .collect();
-            Some(v)
-        }
+        .map_err(|e| Error::MetadataMissing(e.to_string()))?;
+    let raw_title = string(&metadata, "xesam:title");
+    let artist =
+        string_list(&metadata, "xesam:artist").unwrap_or_else(|| "Unknown Artist".to_string());
+    let title = raw_title
+        .clone()
+        .unwrap_or_else(|| "Unknown Title".to_string());
+    let album = string(&metadata, "xesam:album");
+    let album_artist = string_list(&metadata, "xesam:albumArtist");
+    let length = metadata
+        .length_in_microseconds()
+        .filter(|&us| us > 0)
+        .map(|us| us as f64 / 1_000_000.0);
+    // 部分播放器以字符串而不是对象路径发送 trackid
+    let track_id = string(&metadata, "mpris:trackid").filter(|id| id != NO_TRACK);
+    let art_url = string(&metadata, "mpris:artUrl");
+    let track_number = metadata.get("xesam:trackNumber").and_then(|n| match n {
+        MetadataValue::I32(n) => Some(*n),
+        MetadataValue::I64(n) => i32::try_from(*n).ok(),
+        MetadataValue::U32(n) => i32::try_from(*n).ok(),
+        MetadataValue::U64(n) => i32::try_from(*n).ok(),
         _ => None,
     });
-    let artist = artists
-        .unwrap_or_else(|| vec!["Unknown Artist".to_string()])
-        .join(", ");
-    let title = metadata
-        .get("xesam:title")
-        .and_then(|t| match t {
-            mpris::MetadataValue::String(s) => Some(s.to_owned()),
-            _ => None,
-        })
-        .unwrap_or_else(|| "Unknown Title".to_string());
+    let url = string(&metadata, "xesam:url");
     let position = player
         .get_position()
         .map(|d| d.as_secs_f64())
         .unwrap_or(0.0);
-    // Use artist and title as a simple unique ID
-    let id = format!("{} - {}", artist, title);
-    let url = metadata.get("xesam:url").and_then(|u| match u {
-        mpris::MetadataValue::String(s) => Some(s.to_owned()),
-        _ => None,
-    });
-    // Construct SongInfo
-    let si = SongInfo {
-        id: id.clone(),
-        artist: artist.clone(),
-        title: title.clone(),
-        position,
-        status: match status {
-            mpris::PlaybackStatus::Playing => SongStatus::Playing,
-            _ => SongStatus::Paused,
+    let rate = player.get_playback_rate().unwrap_or(1.0);
+    let status = match status {
+        _ if raw_title.is_none() && url.is_none() && track_id.is_none() => SongStatus::NoTrack,
+        mpris::PlaybackStatus::Playing => SongStatus::Playing,
+        mpris::PlaybackStatus::Paused => SongStatus::Paused,
+        mpris::PlaybackStatus::Stopped => SongStatus::Stopped,
+    };
+
+    // 优先用播放器给出的 trackid 和文件地址区分曲目，都没有时退回到标签组合；
+    // 时长用于区分同名的不同录音（现场版与录音室版）
+    let mut id = match (&track_id, &url) {
+        (Some(track_id), Some(url)) => format!("{} {}", track_id, url),
+        (Some(id), None) | (None, Some(id)) => id.clone(),
+        (None, None) => match &album {
+            Some(album) => format!("{} - {} ({})", artist, title, album),
+            None => format!("{} - {}", artist, title),
         },
-        url,
     };
+    if let Some(length) = length {
+        id.push_str(&format!(" [{:.0}]", length));
+    }
 
-    Ok(si)
+    Ok(SongInfo {
+        id,
+        artist,
+        title,
+        album,
+        album_artist,
+        length,
+        track_id,
+        track_number,
+        art_url,
+        position,
+        rate,
+        status,
+        url,
+        player: player_name(player).to_string(),
+        identity: player.identity().to_string(),
+    })
 }
 
-pub fn get_current_song() -> Result<Option<SongInfo>> {
-    match get_songinfo() {
-        Ok(song) => Ok(Some(song)),
-        Err(e) => {
-            // If no player is found, return Ok(None)
-            if e.to_string().contains("Could not find any player") {
-                Ok(None)
-            } else {
-                Err(e)
-            }
-        }
+/// 非空字符串字段
+fn string(metadata: &Metadata, key: &str) -> Option<String> {
+    match metadata.get(key)? {
+        MetadataValue::String(s) if !s.is_empty() => Some(s.to_owned()),
+        _ => None,
     }
 }
+
+/// 字符串或字符串数组字段，多个值用 `, ` 连接
+fn string_list(metadata: &Metadata, key: &str) -> Option<String> {
+    let values: Vec<String> = match metadata.get(key)? {
+        MetadataValue::String(s) => vec![s.to_owned()],
+        MetadataValue::Array(values) => values
+            .iter()
+            .filter_map(|s| match s {
+                MetadataValue::String(ss) => Some(ss.to_owned()),
+                _ => None,
+            })
+            .collect(),
+        _ => return None,
+    };
+    let values: Vec<String> = values.into_iter().filter(|s| !s.is_empty()).collect();
+    (!values.is_empty()).then(|| values.join(", "))
+}
@@ -1,3 +1,15 @@
 pub mod fetcher;
+pub mod mpd;
+pub mod mpris;
+pub mod mpv;
+pub mod select;
+pub mod source;
+pub mod track;
+pub mod watch;
 
 pub use fetcher::get_current_song;
+pub use mpris::MprisSource;
+pub use select::PlayerSelector;
+pub use source::{PlayerSource, sources_from_config};
+pub use track::{TrackChange, TrackTracker};
+pub use watch::{PlayerUpdate, watch};
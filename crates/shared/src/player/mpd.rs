@@ -0,0 +1,568 @@
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpStream;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+use crate::config::{MpdConfig, expand_home};
+use crate::error::{Error, Result};
+use crate::models::{SongInfo, SongStatus};
+use crate::player::source::{Reconnect, parse_track_number};
+use crate::player::{PlayerSource, PlayerUpdate};
+
+const DEFAULT_HOST: &str = "localhost";
+const DEFAULT_PORT: u16 = 6600;
+/// 普通命令的读写超时，`idle` 不设超时
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// TCP 或 Unix 套接字连接
+enum Connection {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl Connection {
+    fn set_timeout(&self, timeout: Option<Duration>) -> std::io::Result<()> {
+        match self {
+            Connection::Tcp(s) => {
+                s.set_read_timeout(timeout)?;
+                s.set_write_timeout(timeout)
+            }
+            Connection::Unix(s) => {
+                s.set_read_timeout(timeout)?;
+                s.set_write_timeout(timeout)
+            }
+        }
+    }
+
+    fn try_clone(&self) -> std::io::Result<Self> {
+        Ok(match self {
+            Connection::Tcp(s) => Connection::Tcp(s.try_clone()?),
+            Connection::Unix(s) => Connection::Unix(s.try_clone()?),
+        })
+    }
+}
+
+impl Read for Connection {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Connection::Tcp(s) => s.read(buf),
+            Connection::Unix(s) => s.read(buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Connection::Tcp(s) => s.write(buf),
+            Connection::Unix(s) => s.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Connection::Tcp(s) => s.flush(),
+            Connection::Unix(s) => s.flush(),
+        }
+    }
+}
+
+/// MPD 协议客户端
+///
+/// 通过 `status` 和 `currentsong` 读取播放状态，`idle player` 等待变化。
+/// 歌曲路径是相对音乐目录的，配合 `music_directory` 映射为本地文件供歌词查找。
+pub struct MpdClient {
+    writer: Connection,
+    reader: BufReader<Connection>,
+    music_directory: Option<PathBuf>,
+}
+
+impl MpdClient {
+    /// 连接并在需要时发送密码
+    pub fn connect(config: &MpdConfig) -> Result<Self> {
+        let (address, env_password) = resolve_address(config.address.as_deref());
+        let is_unix = address.starts_with(['/', '~', '@']);
+        let stream = if let Some(name) = address.strip_prefix('@') {
+            Connection::Unix(connect_abstract(name).map_err(|e| unavailable(&address, e))?)
+        } else if is_unix {
+            let path = expand_home(&address);
+            Connection::Unix(UnixStream::connect(&path).map_err(|e| unavailable(&address, e))?)
+        } else {
+            let target = if has_port(&address) {
+                address.clone()
+            } else {
+                format!("{}:{}", address, DEFAULT_PORT)
+            };
+            Connection::Tcp(TcpStream::connect(&target).map_err(|e| unavailable(&address, e))?)
+        };
+        stream
+            .set_timeout(Some(COMMAND_TIMEOUT))
+            .map_err(|e| unavailable(&address, e))?;
+        let reader = BufReader::new(stream.try_clone().map_err(|e| unavailable(&address, e))?);
+
+        let mut client = Self {
+            writer: stream,
+            reader,
+            music_directory: config.music_directory.as_deref().map(expand_home),
+        };
+
+        let greeting = client.read_line()?;
+        if !greeting.starts_with("OK MPD") {
+            return Err(Error::PlayerUnavailable(format!(
+                "unexpected MPD greeting: {}",
+                greeting
+            )));
+        }
+        if let Some(password) = config.password.as_deref().or(env_password.as_deref()) {
+            client.command(&format!("password {}", quote(password)))?;
+        }
+        if client.music_directory.is_none() && is_unix {
+            // 只有通过 Unix 套接字连接时 MPD 才允许查询配置
+            client.music_directory = client
+                .command("config")
+                .ok()
+                .and_then(|pairs| find(&pairs, "music_directory").map(PathBuf::from));
+        }
+        Ok(client)
+    }
+
+    /// 当前播放状态和歌曲
+    pub fn current_song(&mut self) -> Result<SongInfo> {
+        let status = self.command("status")?;
+        let song = self.command("currentsong")?;
+
+        let state = match find(&status, "state") {
+            Some("play") => SongStatus::Playing,
+            Some("pause") => SongStatus::Paused,
+            _ => SongStatus::Stopped,
+        };
+        let Some(file) = find(&song, "file") else {
+            return Ok(empty_song(SongStatus::NoTrack));
+        };
+
+        let position = find(&status, "elapsed")
+            .and_then(|e| e.parse().ok())
+            .unwrap_or(0.0);
+        let length = find(&status, "duration")
+            .or_else(|| find(&song, "duration"))
+            .and_then(|d| d.parse::<f64>().ok())
+            .or_else(|| find(&song, "Time").and_then(|t| t.parse().ok()))
+            .filter(|&d| d > 0.0);
+        let artist = join_all(&song, "Artist").unwrap_or_else(|| "Unknown Artist".to_string());
+        let title = find(&song, "Title")
+            .map(str::to_string)
+            .or_else(|| file_title(file))
+            .unwrap_or_else(|| "Unknown Title".to_string());
+        let track_id = find(&song, "Id").map(|id| format!("mpd:{}", id));
+        let url = Some(self.local_url(file));
+
+        let mut id = format!("{} {}", track_id.as_deref().unwrap_or("mpd"), file);
+        if let Some(length) = length {
+            id.push_str(&format!(" [{:.0}]", length));
+        }
+
+        Ok(SongInfo {
+            id,
+            artist,
+            title,
+            album: find(&song, "Album").map(str::to_string),
+            album_artist: join_all(&song, "AlbumArtist"),
+            length,
+            track_id,
+            track_number: find(&song, "Track").and_then(parse_track_number),
+            art_url: None,
+            position,
+            rate: 1.0,
+            status: state,
+            url,
+            player: "mpd".to_string(),
+            identity: "Music Player Daemon".to_string(),
+        })
+    }
+
+    /// 阻塞直到播放器状态变化（切歌、暂停、跳转等）
+    pub fn idle(&mut self) -> Result<()> {
+        self.writer
+            .set_timeout(None)
+            .map_err(|e| Error::PlayerUnavailable(e.to_string()))?;
+        let result = self.command("idle player");
+        self.writer
+            .set_timeout(Some(COMMAND_TIMEOUT))
+            .map_err(|e| Error::PlayerUnavailable(e.to_string()))?;
+        result.map(|_| ())
+    }
+
+    /// 歌曲在本地的路径；流媒体等绝对 URL 原样返回
+    fn local_url(&self, file: &str) -> String {
+        if file.contains("://") || file.starts_with('/') {
+            return file.to_string();
+        }
+        match &self.music_directory {
+            Some(dir) => dir.join(file).to_string_lossy().into_owned(),
+            None => file.to_string(),
+        }
+    }
+
+    /// 发送命令并读取响应的键值对，遇到 `ACK` 返回错误
+    fn command(&mut self, command: &str) -> Result<Vec<(String, String)>> {
+        writeln!(self.writer, "{}", command)
+            .and_then(|_| self.writer.flush())
+            .map_err(|e| Error::PlayerUnavailable(format!("MPD: {}", e)))?;
+
+        let mut pairs = Vec::new();
+        loop {
+            let line = self.read_line()?;
+            if line == "OK" {
+                return Ok(pairs);
+            }
+            if let Some(message) = line.strip_prefix("ACK ") {
+                return Err(Error::PlayerUnavailable(format!("MPD: {}", message)));
+            }
+            if let Some((key, value)) = line.split_once(": ") {
+                pairs.push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+
+    fn read_line(&mut self) -> Result<String> {
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => Err(Error::PlayerUnavailable(
+                "MPD closed the connection".to_string(),
+            )),
+            Ok(_) => Ok(line.trim_end_matches(['\r', '\n']).to_string()),
+            Err(e) => Err(Error::PlayerUnavailable(format!("MPD: {}", e))),
+        }
+    }
+}
+
+/// MPD 播放器来源。连接不上时按没有播放器处理并定期重试。
+pub struct MpdSource {
+    config: MpdConfig,
+    client: Option<MpdClient>,
+    reconnect: Reconnect,
+}
+
+impl MpdSource {
+    pub fn new(config: MpdConfig) -> Self {
+        Self {
+            config,
+            client: None,
+            reconnect: Reconnect::default(),
+        }
+    }
+}
+
+impl PlayerSource for MpdSource {
+    fn name(&self) -> &'static str {
+        "mpd"
+    }
+
+    fn snapshot(&mut self) -> PlayerUpdate {
+        let client = match self.client.take() {
+            Some(client) => Ok(client),
+            None => MpdClient::connect(&self.config),
+        };
+        let result = client.and_then(|mut client| {
+            let song = client.current_song()?;
+            Ok((client, song))
+        });
+        match result {
+            Ok((client, song)) => {
+                self.client = Some(client);
+                self.reconnect.connected();
+                PlayerUpdate::single(song, false)
+            }
+            Err(e) => self.reconnect.failed(e),
+        }
+    }
+
+    /// 用 `idle player` 等待变化
+    fn watch(&mut self, tx: &Sender<PlayerUpdate>) {
+        let config = &self.config;
+        self.reconnect.watch(tx, |reconnect, tx| {
+            let mut client = MpdClient::connect(config)?;
+            reconnect.connected();
+            let mut seeked = false;
+            loop {
+                let song = client.current_song()?;
+                if tx.send(PlayerUpdate::single(song, seeked)).is_err() {
+                    return Ok(());
+                }
+                client.idle()?;
+                // `player` 事件包括跳转，进度直接对齐
+                seeked = true;
+            }
+        });
+    }
+}
+
+/// 连接 Linux 抽象套接字，`@mpd` 对应名称 `mpd`
+fn connect_abstract(name: &str) -> std::io::Result<UnixStream> {
+    let address = SocketAddr::from_abstract_name(name)?;
+    UnixStream::connect_addr(&address)
+}
+
+/// 配置地址或 `$MPD_HOST`（可带 `password@` 前缀）、`$MPD_PORT`
+fn resolve_address(configured: Option<&str>) -> (String, Option<String>) {
+    if let Some(address) = configured {
+        return (address.to_string(), None);
+    }
+    let host = std::env::var("MPD_HOST").unwrap_or_else(|_| DEFAULT_HOST.to_string());
+    let (password, host) = match host.split_once('@') {
+        // `@socket` 是抽象套接字，不是密码，`password@@socket` 两者都有
+        Some((password, host)) if !password.is_empty() => (Some(password.to_string()), host),
+        _ => (None, host.as_str()),
+    };
+    if host.starts_with(['/', '~', '@']) || has_port(host) {
+        return (host.to_string(), password);
+    }
+    let port = std::env::var("MPD_PORT")
+        .ok()
+        .and_then(|p| p.parse().ok())
+        .unwrap_or(DEFAULT_PORT);
+    (format!("{}:{}", host, port), password)
+}
+
+fn has_port(address: &str) -> bool {
+    // IPv6 地址需要方括号，例如 `[::1]:6600`
+    match address.rsplit_once(':') {
+        Some((host, port)) => {
+            (!host.contains(':') || host.ends_with(']')) && port.parse::<u16>().is_ok()
+        }
+        None => false,
+    }
+}
+
+fn find<'a>(pairs: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    pairs
+        .iter()
+        .find(|(k, _)| k == key)
+        .map(|(_, v)| v.as_str())
+}
+
+/// 同一个键可以出现多次，例如多位艺术家
+fn join_all(pairs: &[(String, String)], key: &str) -> Option<String> {
+    let values: Vec<&str> = pairs
+        .iter()
+        .filter(|(k, v)| k == key && !v.is_empty())
+        .map(|(_, v)| v.as_str())
+        .collect();
+    (!values.is_empty()).then(|| values.join(", "))
+}
+
+/// 没有标题标签时用文件名
+fn file_title(file: &str) -> Option<String> {
+    Path::new(file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+}
+
+fn quote(arg: &str) -> String {
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn unavailable(address: &str, e: std::io::Error) -> Error {
+    Error::PlayerUnavailable(format!("could not connect to MPD at {}: {}", address, e))
+}
+
+fn empty_song(status: SongStatus) -> SongInfo {
+    SongInfo {
+        id: String::new(),
+        artist: String::new(),
+        title: String::new(),
+        album: None,
+        album_artist: None,
+        length: None,
+        track_id: None,
+        track_number: None,
+        art_url: None,
+        position: 0.0,
+        rate: 1.0,
+        status,
+        url: None,
+        player: "mpd".to_string(),
+        identity: "Music Player Daemon".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::os::unix::net::UnixListener;
+    use std::thread;
+
+    /// 假的 MPD 服务器：按命令原文回复，未知命令回复 `ACK`
+    fn serve(reader: impl Read, mut writer: impl Write, replies: &[(&str, &str)]) {
+        writer.write_all(b"OK MPD 0.23.0\n").unwrap();
+        for line in BufReader::new(reader).lines() {
+            let Ok(command) = line else {
+                return;
+            };
+            let reply = match replies.iter().find(|(c, _)| *c == command) {
+                Some((_, reply)) => reply.to_string(),
+                None => format!("ACK [5@0] {{{}}} unknown command\n", command),
+            };
+            if writer.write_all(reply.as_bytes()).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn unix_server(name: &str, replies: &'static [(&str, &str)]) -> MpdConfig {
+        let path =
+            std::env::temp_dir().join(format!("bard-mpd-{}-{}.sock", std::process::id(), name));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let socket = path.clone();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let _ = std::fs::remove_file(socket);
+            serve(stream.try_clone().unwrap(), stream, replies);
+        });
+        MpdConfig {
+            address: Some(path.to_string_lossy().into_owned()),
+            ..MpdConfig::default()
+        }
+    }
+
+    fn abstract_server(name: &str, replies: &'static [(&str, &str)]) -> MpdConfig {
+        let name = format!("bard-mpd-{}-{}", std::process::id(), name);
+        let address = SocketAddr::from_abstract_name(&name).unwrap();
+        let listener = UnixListener::bind_addr(&address).unwrap();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve(stream.try_clone().unwrap(), stream, replies);
+        });
+        MpdConfig {
+            address: Some(format!("@{}", name)),
+            ..MpdConfig::default()
+        }
+    }
+
+    fn tcp_server(replies: &'static [(&str, &str)]) -> MpdConfig {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            serve(stream.try_clone().unwrap(), stream, replies);
+        });
+        MpdConfig {
+            address: Some(address),
+            ..MpdConfig::default()
+        }
+    }
+
+    #[test]
+    fn current_song_over_unix_socket() {
+        let mut config = unix_server(
+            "song",
+            &[
+                ("password \"secret\"", "OK\n"),
+                (
+                    "status",
+                    "volume: 50\nstate: play\nelapsed: 12.500\nduration: 200.000\nOK\n",
+                ),
+                (
+                    "currentsong",
+                    "file: Album/03 Song.flac\nArtist: A\nArtist: B\nTitle: Song\n\
+                     Album: Record\nTrack: 3/12\nId: 7\nOK\n",
+                ),
+                ("idle player", "changed: player\nOK\n"),
+            ],
+        );
+        config.password = Some("secret".to_string());
+        config.music_directory = Some("/music".to_string());
+
+        let mut client = MpdClient::connect(&config).unwrap();
+        let song = client.current_song().unwrap();
+        assert_eq!(song.status, SongStatus::Playing);
+        assert_eq!(song.artist, "A, B");
+        assert_eq!(song.title, "Song");
+        assert_eq!(song.album.as_deref(), Some("Record"));
+        assert_eq!(song.track_number, Some(3));
+        assert_eq!(song.track_id.as_deref(), Some("mpd:7"));
+        assert_eq!(song.position, 12.5);
+        assert_eq!(song.length, Some(200.0));
+        assert_eq!(song.url.as_deref(), Some("/music/Album/03 Song.flac"));
+        client.idle().unwrap();
+    }
+
+    #[test]
+    fn abstract_socket() {
+        let config = abstract_server(
+            "abstract",
+            &[
+                ("status", "state: pause\nelapsed: 1.000\nOK\n"),
+                ("currentsong", "file: a.flac\nTitle: A\nOK\n"),
+                ("config", "music_directory: /music\nOK\n"),
+            ],
+        );
+        let mut client = MpdClient::connect(&config).unwrap();
+        let song = client.current_song().unwrap();
+        assert_eq!(song.status, SongStatus::Paused);
+        assert_eq!(song.url.as_deref(), Some("/music/a.flac"));
+    }
+
+    #[test]
+    fn state_mapping() {
+        let cases: [(&'static [(&str, &str)], SongStatus); 3] = [
+            (
+                &[
+                    ("status", "state: pause\nOK\n"),
+                    ("currentsong", "file: a.mp3\nOK\n"),
+                ],
+                SongStatus::Paused,
+            ),
+            (
+                &[
+                    ("status", "state: stop\nOK\n"),
+                    ("currentsong", "file: a.mp3\nOK\n"),
+                ],
+                SongStatus::Stopped,
+            ),
+            (
+                &[("status", "state: stop\nOK\n"), ("currentsong", "OK\n")],
+                SongStatus::NoTrack,
+            ),
+        ];
+        for (replies, expected) in cases {
+            let mut client = MpdClient::connect(&tcp_server(replies)).unwrap();
+            assert_eq!(client.current_song().unwrap().status, expected);
+        }
+    }
+
+    #[test]
+    fn title_falls_back_to_file_name() {
+        let config = tcp_server(&[
+            ("status", "state: play\nOK\n"),
+            ("currentsong", "file: Album/03 Song.flac\nTime: 180\nOK\n"),
+        ]);
+        let song = MpdClient::connect(&config).unwrap().current_song().unwrap();
+        assert_eq!(song.artist, "Unknown Artist");
+        assert_eq!(song.title, "03 Song");
+        assert_eq!(song.length, Some(180.0));
+        // 没有音乐目录时保留相对路径
+        assert_eq!(song.url.as_deref(), Some("Album/03 Song.flac"));
+    }
+
+    #[test]
+    fn ack_is_an_error() {
+        let mut config = tcp_server(&[(
+            "password \"wrong\"",
+            "ACK [3@0] {password} incorrect password\n",
+        )]);
+        config.password = Some("wrong".to_string());
+        match MpdClient::connect(&config) {
+            Err(Error::PlayerUnavailable(message)) => {
+                assert!(message.contains("incorrect password"), "{}", message)
+            }
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("connected with a wrong password"),
+        }
+    }
+}
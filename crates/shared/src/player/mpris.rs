@@ -0,0 +1,197 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use dbus::blocking::Connection;
+use dbus::message::MatchRule;
+
+use crate::config::PlayerConfig;
+use crate::error::{Error, Result};
+use crate::player::fetcher::song_from_player;
+use crate::player::select::player_name;
+use crate::player::{PlayerSelector, PlayerSource, PlayerUpdate};
+
+const MPRIS_PATH: &str = "/org/mpris/MediaPlayer2";
+const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+/// 没有信号时的兜底轮询间隔，用于校正进度
+const FALLBACK_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// 无法订阅信号时的轮询间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// 收到信号后等待同一批信号到齐再取快照
+const DEBOUNCE: Duration = Duration::from_millis(20);
+
+/// MPRIS D-Bus 播放器，按 [`PlayerSelector`] 的规则选择和排序
+pub struct MprisSource {
+    selector: PlayerSelector,
+}
+
+impl MprisSource {
+    pub fn new(config: PlayerConfig) -> Self {
+        Self {
+            selector: PlayerSelector::new(config),
+        }
+    }
+
+    /// 取一次快照，`seeked` 是发出 `Seeked` 信号的总线唯一名称
+    fn snapshot_seeked(&mut self, seeked: &[String]) -> PlayerUpdate {
+        let players = match self.selector.players() {
+            Ok(players) => players,
+            Err(e) => return PlayerUpdate::none(e),
+        };
+        // `Seeked` 信号的发送者是唯一名称，需要对应回播放器
+        let seeked = players
+            .iter()
+            .find(|player| seeked.iter().any(|name| name == player.unique_name()))
+            .map(|player| player_name(player).to_string());
+        let mut songs = players.iter().map(song_from_player);
+        let song = songs.next().unwrap_or(Err(Error::NoPlayer));
+        // 其余播放器读取失败时直接跳过
+        let players = song
+            .iter()
+            .cloned()
+            .chain(songs.filter_map(|song| song.ok()))
+            .collect();
+        PlayerUpdate {
+            song,
+            players,
+            seeked,
+        }
+    }
+}
+
+impl Default for MprisSource {
+    fn default() -> Self {
+        Self::new(PlayerConfig::default())
+    }
+}
+
+impl PlayerSource for MprisSource {
+    fn name(&self) -> &'static str {
+        "mpris"
+    }
+
+    fn snapshot(&mut self) -> PlayerUpdate {
+        self.snapshot_seeked(&[])
+    }
+
+    /// 订阅 `PropertiesChanged`、`Seeked` 以及播放器的出现/退出；长时间没有信号时
+    /// 兜底轮询一次。无法订阅信号时退化为每秒轮询。
+    fn watch(&mut self, tx: &Sender<PlayerUpdate>) {
+        loop {
+            let subscription = subscribe();
+            let result = match subscription {
+                Ok((conn, flags)) => watch_signals(&conn, &flags, self, tx),
+                Err(e) => {
+                    eprintln!("bard: {}, falling back to polling", e);
+                    poll_for(self, tx, Duration::from_secs(30))
+                }
+            };
+            if result.is_err() {
+                // 接收端已关闭
+                return;
+            }
+        }
+    }
+}
+
+/// 信号回调置位的标志
+#[derive(Default)]
+struct Flags {
+    changed: AtomicBool,
+    /// 发出 `Seeked` 信号的总线唯一名称
+    seeked: Mutex<Vec<String>>,
+}
+
+/// 连接会话总线并订阅 MPRIS 信号，信号到达时置位标志
+fn subscribe() -> Result<(Connection, Arc<Flags>)> {
+    let dbus_error = |e: dbus::Error| Error::DBusUnavailable(e.to_string());
+    let conn = Connection::new_session().map_err(dbus_error)?;
+    let flags = Arc::new(Flags::default());
+    flags.changed.store(true, Ordering::Relaxed);
+
+    let flag = flags.clone();
+    conn.add_match(
+        MatchRule::new_signal("org.freedesktop.DBus.Properties", "PropertiesChanged")
+            .with_path(MPRIS_PATH),
+        move |(), _: &Connection, _: &dbus::Message| {
+            flag.changed.store(true, Ordering::Relaxed);
+            true
+        },
+    )
+    .map_err(dbus_error)?;
+
+    let flag = flags.clone();
+    conn.add_match(
+        MatchRule::new_signal("org.mpris.MediaPlayer2.Player", "Seeked").with_path(MPRIS_PATH),
+        move |(), _: &Connection, msg: &dbus::Message| {
+            if let Some(sender) = msg.sender() {
+                flag.seeked.lock().unwrap().push(sender.to_string());
+            }
+            flag.changed.store(true, Ordering::Relaxed);
+            true
+        },
+    )
+    .map_err(dbus_error)?;
+
+    let flag = flags.clone();
+    conn.add_match(
+        MatchRule::new_signal("org.freedesktop.DBus", "NameOwnerChanged"),
+        move |(name, _, _): (String, String, String), _: &Connection, _: &dbus::Message| {
+            if name.starts_with(MPRIS_PREFIX) {
+                flag.changed.store(true, Ordering::Relaxed);
+            }
+            true
+        },
+    )
+    .map_err(dbus_error)?;
+
+    Ok((conn, flags))
+}
+
+/// 处理信号直到连接出错；接收端关闭时返回 `Err(())`
+fn watch_signals(
+    conn: &Connection,
+    flags: &Flags,
+    source: &mut MprisSource,
+    tx: &Sender<PlayerUpdate>,
+) -> std::result::Result<(), ()> {
+    let mut last_snapshot = Instant::now();
+    loop {
+        let timeout = FALLBACK_POLL_INTERVAL.saturating_sub(last_snapshot.elapsed());
+        match conn.process(timeout) {
+            Ok(true) => {
+                // 一次切歌会连续发出多个信号，合并后只取一次快照
+                while let Ok(true) = conn.process(DEBOUNCE) {}
+            }
+            Ok(false) => flags.changed.store(true, Ordering::Relaxed),
+            Err(e) => {
+                eprintln!("bard: D-Bus connection lost: {}", e);
+                return Ok(());
+            }
+        }
+
+        if flags.changed.swap(false, Ordering::Relaxed) {
+            let seeked = std::mem::take(&mut *flags.seeked.lock().unwrap());
+            let update = source.snapshot_seeked(&seeked);
+            tx.send(update).map_err(|_| ())?;
+            last_snapshot = Instant::now();
+        }
+    }
+}
+
+/// 轮询一段时间后返回，以便重新尝试订阅
+fn poll_for(
+    source: &mut MprisSource,
+    tx: &Sender<PlayerUpdate>,
+    duration: Duration,
+) -> std::result::Result<(), ()> {
+    let start = Instant::now();
+    while start.elapsed() < duration {
+        tx.send(source.snapshot()).map_err(|_| ())?;
+        thread::sleep(POLL_INTERVAL);
+    }
+    Ok(())
+}
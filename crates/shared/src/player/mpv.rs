@@ -0,0 +1,361 @@
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+
+use crate::config::{MpvConfig, expand_home};
+use crate::error::{Error, Result};
+use crate::models::{SongInfo, SongStatus};
+use crate::player::source::{Reconnect, parse_track_number};
+use crate::player::{PlayerSource, PlayerUpdate};
+
+/// `get_property` 查询的超时
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+/// `time-pos` 每帧都会变化，进度只按这个间隔推送
+const POSITION_INTERVAL: Duration = Duration::from_secs(1);
+/// 切换文件时属性变化陆续到达，等这么久再推送，避免用不完整的元数据查找歌词
+const DEBOUNCE: Duration = Duration::from_millis(50);
+
+/// 监听的属性，顺序即 `observe_property` 的 id；工作目录在前，用于补全相对路径
+const PROPERTIES: &[&str] = &[
+    "working-directory",
+    "time-pos",
+    "pause",
+    "path",
+    "speed",
+    "metadata",
+    "duration",
+    "media-title",
+    "idle-active",
+];
+
+/// 从属性变化事件累积的播放器状态
+#[derive(Debug, Default)]
+struct MpvState {
+    position: f64,
+    paused: bool,
+    path: Option<String>,
+    speed: Option<f64>,
+    metadata: serde_json::Map<String, Value>,
+    duration: Option<f64>,
+    media_title: Option<String>,
+    idle: bool,
+    working_directory: Option<String>,
+}
+
+impl MpvState {
+    /// 应用一次 `property-change` 事件，返回是否需要立即推送
+    fn apply(&mut self, name: &str, data: &Value) -> bool {
+        match name {
+            "time-pos" => {
+                self.position = data.as_f64().unwrap_or(0.0);
+                return false;
+            }
+            "pause" => self.paused = data.as_bool().unwrap_or(false),
+            "path" => {
+                self.path = data.as_str().map(str::to_string);
+                // 新文件从头开始，旧进度不再有效
+                self.position = 0.0;
+            }
+            "speed" => self.speed = data.as_f64(),
+            "metadata" => self.metadata = data.as_object().cloned().unwrap_or_default(),
+            "duration" => self.duration = data.as_f64(),
+            "media-title" => self.media_title = data.as_str().map(str::to_string),
+            "idle-active" => self.idle = data.as_bool().unwrap_or(false),
+            "working-directory" => self.working_directory = data.as_str().map(str::to_string),
+            _ => return false,
+        }
+        true
+    }
+
+    /// 标签键名大小写不固定（`title`、`TITLE`），按不区分大小写查找
+    fn tag(&self, key: &str) -> Option<String> {
+        self.metadata
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .and_then(|(_, v)| v.as_str())
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+    }
+
+    /// 相对路径按 mpv 的工作目录补全，URL 原样保留
+    fn url(&self) -> Option<String> {
+        let path = self.path.as_ref()?;
+        if path.contains("://") || path.starts_with('/') {
+            return Some(path.clone());
+        }
+        match &self.working_directory {
+            Some(dir) => Some(Path::new(dir).join(path).to_string_lossy().into_owned()),
+            None => Some(path.clone()),
+        }
+    }
+
+    fn song(&self) -> SongInfo {
+        let status = if self.idle || self.path.is_none() {
+            SongStatus::NoTrack
+        } else if self.paused {
+            SongStatus::Paused
+        } else {
+            SongStatus::Playing
+        };
+        let url = self.url();
+        let length = self.duration.filter(|&d| d > 0.0);
+        // 时长在切换文件后才到达，不放进 id 以免同一首歌加载两次
+        let id = url.clone().unwrap_or_default();
+
+        SongInfo {
+            id,
+            artist: self
+                .tag("artist")
+                .unwrap_or_else(|| "Unknown Artist".to_string()),
+            title: self
+                .tag("title")
+                .or_else(|| self.media_title.clone())
+                .unwrap_or_else(|| "Unknown Title".to_string()),
+            album: self.tag("album"),
+            album_artist: self.tag("album_artist"),
+            length,
+            track_id: None,
+            track_number: self.tag("track").as_deref().and_then(parse_track_number),
+            art_url: None,
+            position: self.position,
+            rate: self.speed.unwrap_or(1.0),
+            status,
+            url,
+            player: "mpv".to_string(),
+            identity: "mpv".to_string(),
+        }
+    }
+}
+
+/// mpv IPC 播放器来源。mpv 未运行时按没有播放器处理并定期重试。
+pub struct MpvSource {
+    config: MpvConfig,
+    reconnect: Reconnect,
+}
+
+impl MpvSource {
+    pub fn new(config: MpvConfig) -> Self {
+        Self {
+            config,
+            reconnect: Reconnect::default(),
+        }
+    }
+
+    /// 用 `get_property` 逐个读取属性
+    fn query(&self) -> Result<SongInfo> {
+        let socket = expand_home(&self.config.socket);
+        let unavailable = |e: std::io::Error| {
+            Error::PlayerUnavailable(format!("mpv IPC {}: {}", socket.display(), e))
+        };
+        let mut writer = UnixStream::connect(&socket).map_err(unavailable)?;
+        writer
+            .set_read_timeout(Some(QUERY_TIMEOUT))
+            .map_err(unavailable)?;
+        let reader = BufReader::new(writer.try_clone().map_err(unavailable)?);
+        for (id, name) in PROPERTIES.iter().enumerate() {
+            let command = json!({ "command": ["get_property", name], "request_id": id + 1 });
+            writeln!(writer, "{}", command).map_err(unavailable)?;
+        }
+
+        let mut state = MpvState::default();
+        let mut pending = PROPERTIES.len();
+        for line in reader.lines() {
+            let line = line.map_err(unavailable)?;
+            let Ok(message) = serde_json::from_str::<Value>(&line) else {
+                continue;
+            };
+            // 事件没有 request_id，跳过
+            let Some(id) = message["request_id"].as_u64() else {
+                continue;
+            };
+            if let Some(name) = PROPERTIES.get((id as usize).wrapping_sub(1)) {
+                // 属性不可用时 `data` 为空，按未设置处理
+                state.apply(name, &message["data"]);
+                pending -= 1;
+            }
+            if pending == 0 {
+                return Ok(state.song());
+            }
+        }
+        Err(Error::PlayerUnavailable(
+            "mpv closed the IPC socket".to_string(),
+        ))
+    }
+}
+
+impl PlayerSource for MpvSource {
+    fn name(&self) -> &'static str {
+        "mpv"
+    }
+
+    fn snapshot(&mut self) -> PlayerUpdate {
+        match self.query() {
+            Ok(song) => {
+                self.reconnect.connected();
+                PlayerUpdate::single(song, false)
+            }
+            Err(e) => self.reconnect.failed(e),
+        }
+    }
+
+    /// 用 `observe_property` 接收属性变化
+    fn watch(&mut self, tx: &Sender<PlayerUpdate>) {
+        let socket = expand_home(&self.config.socket);
+        self.reconnect
+            .watch(tx, |reconnect, tx| watch_socket(&socket, tx, reconnect));
+    }
+}
+
+/// 处理一个连接直到 mpv 退出；接收端关闭时返回 `Ok`
+fn watch_socket(socket: &Path, tx: &Sender<PlayerUpdate>, reconnect: &mut Reconnect) -> Result<()> {
+    let unavailable = |e: std::io::Error| {
+        Error::PlayerUnavailable(format!("mpv IPC {}: {}", socket.display(), e))
+    };
+    let mut writer = UnixStream::connect(socket).map_err(unavailable)?;
+    let mut reader = BufReader::new(writer.try_clone().map_err(unavailable)?);
+    reconnect.connected();
+
+    for (id, name) in PROPERTIES.iter().enumerate() {
+        let command = json!({ "command": ["observe_property", id + 1, name] });
+        writeln!(writer, "{}", command).map_err(unavailable)?;
+    }
+
+    let mut state = MpvState::default();
+    let mut line = Vec::new();
+    let mut last_sent: Option<Instant> = None;
+    // 有未推送的变化，以及第一次变化的时间
+    let mut dirty_since: Option<Instant> = None;
+    let mut seeking = false;
+    let mut seeked = false;
+    loop {
+        // 有未推送的变化时带超时读取，超时即可推送
+        let timeout = dirty_since.map(|_| DEBOUNCE);
+        reader
+            .get_ref()
+            .set_read_timeout(timeout)
+            .map_err(unavailable)?;
+        // 超时时已读到的半行保留在 `line` 中，下次继续
+        let timed_out = match reader.read_until(b'\n', &mut line) {
+            Ok(0) => {
+                return Err(Error::PlayerUnavailable(
+                    "mpv closed the IPC socket".to_string(),
+                ));
+            }
+            Ok(_) => {
+                if let Ok(message) = serde_json::from_slice::<Value>(&line) {
+                    match message["event"].as_str() {
+                        Some("property-change") => {
+                            let name = message["name"].as_str().unwrap_or_default();
+                            if state.apply(name, &message["data"]) {
+                                dirty_since.get_or_insert_with(Instant::now);
+                            }
+                            if name == "time-pos" && seeking {
+                                // 跳转后的第一个进度，直接对齐
+                                seeking = false;
+                                seeked = true;
+                                dirty_since.get_or_insert_with(Instant::now);
+                            }
+                        }
+                        Some("seek") | Some("playback-restart") => seeking = true,
+                        _ => {}
+                    }
+                }
+                line.clear();
+                false
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => true,
+            Err(e) => return Err(unavailable(e)),
+        };
+
+        let settled = timed_out || dirty_since.is_some_and(|since| since.elapsed() >= DEBOUNCE);
+        let due = last_sent.is_none_or(|sent| sent.elapsed() >= POSITION_INTERVAL);
+        if (dirty_since.is_some() && settled) || (dirty_since.is_none() && due) {
+            let song = state.song();
+            if tx.send(PlayerUpdate::single(song, seeked)).is_err() {
+                return Ok(());
+            }
+            seeked = false;
+            dirty_since = None;
+            last_sent = Some(Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(events: &[(&str, Value)]) -> MpvState {
+        let mut state = MpvState::default();
+        for (name, data) in events {
+            state.apply(name, data);
+        }
+        state
+    }
+
+    #[test]
+    fn relative_path_joins_working_directory() {
+        let state = state(&[
+            ("working-directory", json!("/home/user/Music")),
+            ("path", json!("Album/03 Song.flac")),
+            ("pause", json!(false)),
+            (
+                "metadata",
+                json!({"ARTIST": "A", "TITLE": "Song", "album": "Record", "track": "3/12"}),
+            ),
+            ("duration", json!(200.5)),
+            ("time-pos", json!(12.5)),
+        ]);
+        let song = state.song();
+        assert_eq!(song.status, SongStatus::Playing);
+        assert_eq!(
+            song.url.as_deref(),
+            Some("/home/user/Music/Album/03 Song.flac")
+        );
+        assert_eq!(song.id, "/home/user/Music/Album/03 Song.flac");
+        assert_eq!(song.artist, "A");
+        assert_eq!(song.title, "Song");
+        assert_eq!(song.album.as_deref(), Some("Record"));
+        assert_eq!(song.track_number, Some(3));
+        assert_eq!(song.length, Some(200.5));
+        assert_eq!(song.position, 12.5);
+    }
+
+    #[test]
+    fn urls_and_absolute_paths_are_kept() {
+        for path in ["https://example.com/stream", "/music/a.mp3"] {
+            let state = state(&[
+                ("working-directory", json!("/home/user")),
+                ("path", json!(path)),
+            ]);
+            assert_eq!(state.song().url.as_deref(), Some(path));
+        }
+    }
+
+    #[test]
+    fn idle_has_no_track() {
+        let mut state = state(&[("path", json!("/music/a.mp3")), ("pause", json!(true))]);
+        assert_eq!(state.song().status, SongStatus::Paused);
+        assert!(state.apply("idle-active", &json!(true)));
+        assert_eq!(state.song().status, SongStatus::NoTrack);
+        // 属性不可用时为空，按未设置处理
+        state.apply("idle-active", &json!(false));
+        state.apply("path", &Value::Null);
+        assert_eq!(state.song().status, SongStatus::NoTrack);
+    }
+
+    #[test]
+    fn new_path_resets_position() {
+        let mut state = state(&[("path", json!("/music/a.mp3")), ("time-pos", json!(30.0))]);
+        assert!(!state.apply("time-pos", &json!(31.0)));
+        state.apply("path", &json!("/music/b.mp3"));
+        assert_eq!(state.song().position, 0.0);
+        // 没有标题标签时用 `media-title`
+        state.apply("media-title", &json!("b"));
+        assert_eq!(state.song().title, "b");
+    }
+}
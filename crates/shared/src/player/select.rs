@@ -0,0 +1,259 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use mpris::{PlaybackStatus, Player, PlayerFinder};
+
+use crate::config::PlayerConfig;
+use crate::error::{Error, Result};
+use crate::models::SongInfo;
+use crate::player::fetcher::song_from_player;
+
+const MPRIS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+
+/// 按配置规则在多个 MPRIS 播放器中选出要显示的一个
+///
+/// 先过滤 `players` 和 `blocklist`，然后依次比较：是否正在播放、`priority` 中的位置、
+/// 最近一次开始播放的时间（类似 playerctld 跟随最近活动的播放器）。
+pub struct PlayerSelector {
+    config: PlayerConfig,
+    /// 每个播放器最近一次开始播放的时间
+    last_started: HashMap<String, Instant>,
+    /// 上一次看到的播放状态
+    was_playing: HashMap<String, bool>,
+}
+
+impl PlayerSelector {
+    pub fn new(config: PlayerConfig) -> Self {
+        Self {
+            config,
+            last_started: HashMap::new(),
+            was_playing: HashMap::new(),
+        }
+    }
+
+    /// 当前选中播放器的歌曲信息，没有符合条件的播放器时返回 [`Error::NoPlayer`]
+    pub fn current_song(&mut self) -> Result<SongInfo> {
+        let player = self.players()?.into_iter().next().ok_or(Error::NoPlayer)?;
+        song_from_player(&player)
+    }
+
+    /// 所有符合条件的播放器，按选择顺序排列，第一个即 [`current_song`](Self::current_song) 选中的播放器
+    pub fn players(&mut self) -> Result<Vec<Player>> {
+        let finder = PlayerFinder::new().map_err(|e| Error::DBusUnavailable(e.to_string()))?;
+        let players = finder
+            .iter_players()?
+            // 单个播放器无响应时跳过它
+            .filter_map(|player| player.ok())
+            .filter(|player| self.is_allowed(player_name(player), player.identity()))
+            .filter_map(|player| {
+                let status = player.get_playback_status().ok()?;
+                Some((player, status))
+            })
+            .collect();
+        Ok(self.rank(players, |player| (player_name(player), player.identity())))
+    }
+
+    /// 按播放状态、`priority` 和最近开始播放的时间排序，`names` 给出播放器名和 Identity
+    fn rank<T>(
+        &mut self,
+        players: Vec<(T, PlaybackStatus)>,
+        names: impl Fn(&T) -> (&str, &str),
+    ) -> Vec<T> {
+        let now = Instant::now();
+        for (player, status) in &players {
+            let playing = *status == PlaybackStatus::Playing;
+            let was_playing = self
+                .was_playing
+                .insert(names(player).0.to_string(), playing)
+                .unwrap_or(false);
+            if playing && !was_playing {
+                self.last_started.insert(names(player).0.to_string(), now);
+            }
+        }
+        // 已退出的播放器不再保留状态
+        self.was_playing
+            .retain(|known, _| players.iter().any(|(p, _)| names(p).0 == known));
+        self.last_started
+            .retain(|known, _| players.iter().any(|(p, _)| names(p).0 == known));
+
+        let mut ranked: Vec<_> = players
+            .into_iter()
+            .enumerate()
+            .map(|(index, (player, status))| {
+                let status_rank = match status {
+                    PlaybackStatus::Playing => 0,
+                    PlaybackStatus::Paused => 1,
+                    PlaybackStatus::Stopped => 2,
+                };
+                let priority = self
+                    .config
+                    .priority
+                    .iter()
+                    .position(|pattern| {
+                        let (name, identity) = names(&player);
+                        matches_name(pattern, name, identity)
+                    })
+                    .unwrap_or(self.config.priority.len());
+                let started = self.last_started.get(names(&player).0).copied();
+                let key = (status_rank, priority, std::cmp::Reverse(started), index);
+                (key, player)
+            })
+            .collect();
+        ranked.sort_by_key(|(key, _)| *key);
+        ranked.into_iter().map(|(_, player)| player).collect()
+    }
+
+    fn is_allowed(&self, name: &str, identity: &str) -> bool {
+        let matches = |pattern: &String| matches_name(pattern, name, identity);
+        let allowed = self.config.players.is_empty() || self.config.players.iter().any(matches);
+        let blocked = self.config.blocklist.iter().any(matches);
+        allowed && !blocked
+    }
+}
+
+impl Default for PlayerSelector {
+    fn default() -> Self {
+        Self::new(PlayerConfig::default())
+    }
+}
+
+/// 去掉 `org.mpris.MediaPlayer2.` 前缀的总线名
+pub(crate) fn player_name(player: &Player) -> &str {
+    player
+        .bus_name()
+        .strip_prefix(MPRIS_PREFIX)
+        .unwrap_or(player.bus_name())
+}
+
+/// 模式匹配播放器名称（忽略 `.instance…` 后缀）或 Identity
+pub(crate) fn matches_name(pattern: &str, name: &str, identity: &str) -> bool {
+    let base = name.split('.').next().unwrap_or(name);
+    [name, base, identity]
+        .iter()
+        .any(|candidate| glob_match(pattern, candidate))
+}
+
+/// 不区分大小写的 `*` 通配匹配
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let text = text.to_lowercase();
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // 没有通配符时必须完全相等
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|p| p.to_string()).collect()
+    }
+
+    /// (播放器名, Identity) 和播放状态
+    fn rank(
+        selector: &mut PlayerSelector,
+        players: &[(&'static str, &'static str, PlaybackStatus)],
+    ) -> Vec<&'static str> {
+        let players = players
+            .iter()
+            .map(|(name, identity, status)| ((*name, *identity), *status))
+            .collect();
+        selector
+            .rank(players, |(name, identity)| (name, identity))
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    #[test]
+    fn glob_patterns() {
+        assert!(glob_match("spotify", "Spotify"));
+        assert!(!glob_match("spotify", "spotifyd"));
+        assert!(glob_match("spot*", "spotifyd"));
+        assert!(glob_match("*fire*", "Firefox"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("a*b*c", "aXbYc"));
+        assert!(!glob_match("a*b*c", "aXcYb"));
+        // 前后缀不能重叠
+        assert!(!glob_match("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn names_match_without_instance_suffix() {
+        assert!(matches_name(
+            "firefox",
+            "firefox.instance_1_23",
+            "Mozilla Firefox"
+        ));
+        assert!(matches_name(
+            "*Firefox",
+            "firefox.instance_1_23",
+            "Mozilla Firefox"
+        ));
+        assert!(!matches_name(
+            "chromium",
+            "firefox.instance_1_23",
+            "Mozilla Firefox"
+        ));
+    }
+
+    #[test]
+    fn allow_and_block_lists() {
+        let selector = PlayerSelector::new(PlayerConfig {
+            players: patterns(&["mpv", "spotify"]),
+            blocklist: patterns(&["spot*"]),
+            ..PlayerConfig::default()
+        });
+        assert!(selector.is_allowed("mpv", "mpv"));
+        assert!(!selector.is_allowed("spotify", "Spotify"));
+        assert!(!selector.is_allowed("vlc", "VLC media player"));
+        assert!(PlayerSelector::default().is_allowed("vlc", "VLC media player"));
+    }
+
+    #[test]
+    fn playing_then_priority_then_order() {
+        use PlaybackStatus::*;
+        let mut selector = PlayerSelector::new(PlayerConfig {
+            priority: patterns(&["VLC*", "mpv"]),
+            ..PlayerConfig::default()
+        });
+        let order = rank(
+            &mut selector,
+            &[
+                ("firefox", "Firefox", Paused),
+                ("mpv", "mpv", Paused),
+                ("cmus", "cmus", Stopped),
+                ("vlc", "VLC media player", Paused),
+                ("spotify", "Spotify", Playing),
+            ],
+        );
+        assert_eq!(order, ["spotify", "vlc", "mpv", "firefox", "cmus"]);
+    }
+
+    #[test]
+    fn most_recently_started_wins() {
+        use PlaybackStatus::*;
+        let mut selector = PlayerSelector::new(PlayerConfig::default());
+        let first = rank(&mut selector, &[("a", "a", Playing), ("b", "b", Paused)]);
+        assert_eq!(first, ["a", "b"]);
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        // b 之后才开始播放，排在一直在播放的 a 前面
+        let second = rank(&mut selector, &[("a", "a", Playing), ("b", "b", Playing)]);
+        assert_eq!(second, ["b", "a"]);
+    }
+}
@@ -0,0 +1,239 @@
+use std::process::Command;
+use std::sync::mpsc::Sender;
+use std::thread;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::config::{CommandConfig, PlayerConfig, SourceKind};
+use crate::error::{Error, Result};
+use crate::models::{SongInfo, SongStatus};
+use crate::player::PlayerUpdate;
+use crate::player::mpd::MpdSource;
+use crate::player::mpris::MprisSource;
+use crate::player::mpv::MpvSource;
+
+/// 默认实现的轮询间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// 连接失败后的重试间隔
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 播放器来源，例如 MPRIS、MPD、mpv
+///
+/// 每个来源可以报告多个播放器，`players` 按来源内部的优先顺序排列。
+/// 多个来源的结果由 [`watch`](crate::player::watch) 合并。
+pub trait PlayerSource: Send {
+    /// 来源名称，用于日志
+    fn name(&self) -> &'static str;
+
+    /// 读取一次当前状态
+    fn snapshot(&mut self) -> PlayerUpdate;
+
+    /// 监听变化并推送更新，阻塞当前线程，接收端关闭后返回
+    ///
+    /// 默认每秒轮询一次 [`snapshot`](Self::snapshot)。
+    fn watch(&mut self, tx: &Sender<PlayerUpdate>) {
+        while tx.send(self.snapshot()).is_ok() {
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+/// 需要保持连接的来源（MPD、mpv）的重连状态
+///
+/// 连接不上时按没有播放器处理并定期重试，错误只在第一次失败时打印，恢复前不再重复。
+#[derive(Debug, Default)]
+pub(crate) struct Reconnect {
+    reported: bool,
+}
+
+impl Reconnect {
+    /// 连接成功，下次失败时重新报告
+    pub(crate) fn connected(&mut self) {
+        self.reported = false;
+    }
+
+    /// 报告连接错误，返回没有播放器的更新
+    pub(crate) fn failed(&mut self, e: Error) -> PlayerUpdate {
+        if !self.reported {
+            eprintln!("bard: {}", e);
+            self.reported = true;
+        }
+        PlayerUpdate::none(Error::NoPlayer)
+    }
+
+    /// 反复运行 `session` 直到接收端关闭
+    ///
+    /// `session` 连接并推送更新，接收端关闭时返回 `Ok`，连接断开时返回错误。
+    pub(crate) fn watch(
+        &mut self,
+        tx: &Sender<PlayerUpdate>,
+        mut session: impl FnMut(&mut Self, &Sender<PlayerUpdate>) -> Result<()>,
+    ) {
+        loop {
+            match session(self, tx) {
+                Ok(()) => return,
+                Err(e) => {
+                    if tx.send(self.failed(e)).is_err() {
+                        return;
+                    }
+                }
+            }
+            thread::sleep(RECONNECT_INTERVAL);
+        }
+    }
+}
+
+/// 曲目编号，可能带总数，例如 `3/12`
+pub(crate) fn parse_track_number(track: &str) -> Option<i32> {
+    track.split('/').next()?.trim().parse().ok()
+}
+
+/// 按配置创建播放器来源，顺序与 `sources` 一致
+pub fn sources_from_config(config: &PlayerConfig) -> Vec<Box<dyn PlayerSource>> {
+    config
+        .sources
+        .iter()
+        .map(|kind| -> Box<dyn PlayerSource> {
+            match kind {
+                SourceKind::Mpris => Box::new(MprisSource::new(config.clone())),
+                SourceKind::Mpd => Box::new(MpdSource::new(config.mpd.clone())),
+                SourceKind::Mpv => Box::new(MpvSource::new(config.mpv.clone())),
+                SourceKind::Command => Box::new(CommandSource::new(config.command.clone())),
+            }
+        })
+        .collect()
+}
+
+/// 运行外部命令获取播放状态
+///
+/// 命令输出一个 JSON 对象（或对象数组，每个播放器一个），例如
+/// `{"artist": "A", "title": "B", "position": 12.3, "status": "playing", "url": "/music/b.flac"}`；
+/// 没有输出表示没有播放器。
+pub struct CommandSource {
+    config: CommandConfig,
+}
+
+impl CommandSource {
+    pub fn new(config: CommandConfig) -> Self {
+        Self { config }
+    }
+
+    fn run(&self) -> Result<Vec<SongInfo>> {
+        if self.config.command.trim().is_empty() {
+            return Err(Error::PlayerUnavailable(
+                "player.command.command is not set".to_string(),
+            ));
+        }
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(&self.config.command)
+            .output()
+            .map_err(|e| Error::PlayerUnavailable(format!("{}: {}", self.config.command, e)))?;
+        if !output.status.success() {
+            return Err(Error::PlayerUnavailable(format!(
+                "{} exited with {}",
+                self.config.command, output.status
+            )));
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let songs: CommandOutput = serde_json::from_str(&stdout)
+            .map_err(|e| Error::MetadataMissing(format!("{}: {}", self.config.command, e)))?;
+        Ok(match songs {
+            CommandOutput::One(song) => vec![song.into()],
+            CommandOutput::Many(songs) => songs.into_iter().map(Into::into).collect(),
+        })
+    }
+}
+
+impl PlayerSource for CommandSource {
+    fn name(&self) -> &'static str {
+        "command"
+    }
+
+    fn snapshot(&mut self) -> PlayerUpdate {
+        match self.run() {
+            Ok(players) => PlayerUpdate {
+                song: players.first().cloned().ok_or(Error::NoPlayer),
+                players,
+                seeked: None,
+            },
+            Err(e) => PlayerUpdate::none(e),
+        }
+    }
+
+    fn watch(&mut self, tx: &Sender<PlayerUpdate>) {
+        let interval = Duration::from_secs_f64(self.config.interval.max(0.1));
+        while tx.send(self.snapshot()).is_ok() {
+            thread::sleep(interval);
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CommandOutput {
+    One(CommandSong),
+    Many(Vec<CommandSong>),
+}
+
+/// 命令输出的字段，除标题外都可以省略
+#[derive(Deserialize)]
+struct CommandSong {
+    #[serde(default)]
+    artist: Option<String>,
+    title: String,
+    #[serde(default)]
+    album: Option<String>,
+    #[serde(default)]
+    length: Option<f64>,
+    #[serde(default)]
+    position: f64,
+    #[serde(default)]
+    rate: Option<f64>,
+    /// `playing`、`paused` 或 `stopped`，默认 `playing`
+    #[serde(default)]
+    status: Option<String>,
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    player: Option<String>,
+}
+
+impl From<CommandSong> for SongInfo {
+    fn from(song: CommandSong) -> Self {
+        let artist = song.artist.unwrap_or_else(|| "Unknown Artist".to_string());
+        let mut id = match &song.url {
+            Some(url) => url.clone(),
+            None => format!("{} - {}", artist, song.title),
+        };
+        if let Some(length) = song.length {
+            id.push_str(&format!(" [{:.0}]", length));
+        }
+        let player = song.player.unwrap_or_else(|| "command".to_string());
+        SongInfo {
+            id,
+            artist,
+            title: song.title,
+            album: song.album,
+            album_artist: None,
+            length: song.length,
+            track_id: None,
+            track_number: None,
+            art_url: None,
+            position: song.position,
+            rate: song.rate.unwrap_or(1.0),
+            status: match song.status.as_deref() {
+                Some("paused") => SongStatus::Paused,
+                Some("stopped") => SongStatus::Stopped,
+                _ => SongStatus::Playing,
+            },
+            url: song.url,
+            identity: player.clone(),
+            player,
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use crate::clock::PlaybackClock;
+use crate::models::SongInfo;
+
+/// 进度跳回到这个位置之前才可能是重新播放（秒）
+const RESTART_WINDOW: f64 = 5.0;
+/// 跳回前离结尾不到这么多才算重新播放，其余情况视为普通的向后跳转（秒）
+const END_WINDOW: f64 = 10.0;
+
+/// 一次更新相对上一次的曲目变化
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackChange {
+    /// 仍是同一首，继续播放
+    Same,
+    /// 换了一首（或第一次看到）
+    New,
+    /// 同一首从头重新播放，例如单曲循环或再次点播
+    Restarted,
+}
+
+/// 记录一个播放器上次的曲目，判断歌曲是否切换或重新开始
+///
+/// 曲目以 [`SongInfo::id`] 区分（由 trackid、url 和时长构成）。同一首歌快要
+/// 播完时进度跳回开头附近视为重新播放；时长未知时不做判断。
+#[derive(Debug, Default)]
+pub struct TrackTracker {
+    id: Option<String>,
+}
+
+impl TrackTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `clock` 是这个播放器应用本次更新之前的进度时钟
+    pub fn update(&mut self, song: &SongInfo, clock: &PlaybackClock) -> TrackChange {
+        if self.id.as_deref() != Some(song.id.as_str()) {
+            self.id = Some(song.id.clone());
+            return TrackChange::New;
+        }
+        let near_end = song
+            .length
+            .is_some_and(|length| clock.now() > length - END_WINDOW);
+        if near_end && song.position < RESTART_WINDOW {
+            return TrackChange::Restarted;
+        }
+        TrackChange::Same
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::song;
+
+    fn clock_at(position: f64) -> PlaybackClock {
+        let mut clock = PlaybackClock::new();
+        clock.update(position, 1.0, false);
+        clock
+    }
+
+    fn song_at(id: &str, position: f64) -> SongInfo {
+        SongInfo {
+            id: id.to_string(),
+            length: Some(200.0),
+            position,
+            ..song("artist", "title")
+        }
+    }
+
+    #[test]
+    fn first_and_changed_tracks_are_new() {
+        let mut tracker = TrackTracker::new();
+        let clock = clock_at(0.0);
+        assert_eq!(tracker.update(&song_at("a", 0.0), &clock), TrackChange::New);
+        assert_eq!(
+            tracker.update(&song_at("a", 1.0), &clock),
+            TrackChange::Same
+        );
+        assert_eq!(tracker.update(&song_at("b", 1.0), &clock), TrackChange::New);
+    }
+
+    #[test]
+    fn jump_back_from_the_end_is_a_restart() {
+        let mut tracker = TrackTracker::new();
+        tracker.update(&song_at("a", 195.0), &clock_at(195.0));
+        let change = tracker.update(&song_at("a", 0.5), &clock_at(195.0));
+        assert_eq!(change, TrackChange::Restarted);
+    }
+
+    #[test]
+    fn jump_back_mid_song_is_not_a_restart() {
+        let mut tracker = TrackTracker::new();
+        tracker.update(&song_at("a", 60.0), &clock_at(60.0));
+        assert_eq!(
+            tracker.update(&song_at("a", 0.5), &clock_at(60.0)),
+            TrackChange::Same
+        );
+        // 离结尾很近但没有跳回开头
+        assert_eq!(
+            tracker.update(&song_at("a", 30.0), &clock_at(195.0)),
+            TrackChange::Same
+        );
+    }
+
+    #[test]
+    fn unknown_length_never_restarts() {
+        let mut tracker = TrackTracker::new();
+        let song = |position| SongInfo {
+            length: None,
+            ..song_at("a", position)
+        };
+        tracker.update(&song(195.0), &clock_at(195.0));
+        assert_eq!(
+            tracker.update(&song(0.5), &clock_at(195.0)),
+            TrackChange::Same
+        );
+    }
+}
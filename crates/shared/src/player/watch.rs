@@ -0,0 +1,289 @@
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Instant;
+
+use crate::config::PlayerConfig;
+use crate::error::{Error, Result};
+use crate::models::{SongInfo, SongStatus};
+use crate::player::select::matches_name;
+use crate::player::{PlayerSource, sources_from_config};
+
+/// 播放器推送的一次更新
+#[derive(Debug)]
+pub struct PlayerUpdate {
+    /// 选中的播放器
+    pub song: Result<SongInfo>,
+    /// 所有符合条件的播放器，按选择顺序排列，包括 `song`
+    pub players: Vec<SongInfo>,
+    /// 发生跳转的播放器，它的进度应直接跳转而不是平滑修正
+    pub seeked: Option<String>,
+}
+
+impl PlayerUpdate {
+    /// 没有可用的播放器
+    pub fn none(error: Error) -> Self {
+        Self {
+            song: Err(error),
+            players: Vec::new(),
+            seeked: None,
+        }
+    }
+
+    /// 只有一个播放器的来源，`seeked` 表示这次更新由跳转触发
+    pub fn single(song: SongInfo, seeked: bool) -> Self {
+        Self {
+            players: vec![song.clone()],
+            seeked: seeked.then(|| song.player.clone()),
+            song: Ok(song),
+        }
+    }
+}
+
+/// 监听配置中的所有播放器来源，任一来源变化时推送合并后的快照
+///
+/// 合并时先按 `players`、`blocklist` 过滤，再依次比较是否正在播放和 `priority` 中的位置，
+/// 其余保持来源内部的顺序和 `sources` 的顺序。只配置一个来源时原样传递它的错误；
+/// 多个来源时某个来源不可用按没有播放器处理。阻塞当前线程，接收端关闭后返回。
+pub fn watch(config: PlayerConfig, tx: Sender<PlayerUpdate>) {
+    let sources = sources_from_config(&config);
+    watch_sources(&config, sources, tx);
+}
+
+/// 监听给定的来源并合并，见 [`watch`]
+fn watch_sources(
+    config: &PlayerConfig,
+    sources: Vec<Box<dyn PlayerSource>>,
+    tx: Sender<PlayerUpdate>,
+) {
+    let source_count = sources.len();
+    let (merged_tx, merged_rx) = mpsc::channel::<(usize, PlayerUpdate)>();
+    for (index, mut source) in sources.into_iter().enumerate() {
+        let (source_tx, source_rx) = mpsc::channel();
+        let merged_tx = merged_tx.clone();
+        thread::spawn(move || {
+            for update in source_rx {
+                if merged_tx.send((index, update)).is_err() {
+                    return;
+                }
+            }
+        });
+        thread::spawn(move || source.watch(&source_tx));
+    }
+    drop(merged_tx);
+
+    // 每个来源最近一次报告的播放器及报告时间
+    let mut latest: Vec<(Instant, Vec<SongInfo>)> =
+        vec![(Instant::now(), Vec::new()); source_count];
+    for (index, update) in merged_rx {
+        let error = match update.song {
+            Err(Error::NoPlayer) | Ok(_) => None,
+            Err(e) => Some(e),
+        };
+        latest[index] = (Instant::now(), update.players);
+
+        let mut players: Vec<SongInfo> = latest
+            .iter()
+            .flat_map(|(reported, players)| players.iter().map(move |song| (reported, song)))
+            .filter(|(_, song)| is_allowed(config, song))
+            .map(|(reported, song)| {
+                // 其他来源的进度是之前报告的，推算到现在，避免把时钟拉回去
+                let mut song = song.clone();
+                if song.status == SongStatus::Playing {
+                    song.position += reported.elapsed().as_secs_f64() * song.rate;
+                }
+                song
+            })
+            .collect();
+        // 稳定排序，保留来源内部的顺序（例如 MPRIS 最近开始播放的优先）
+        players.sort_by_key(|song| {
+            let status_rank = match song.status {
+                SongStatus::Playing => 0,
+                SongStatus::Paused => 1,
+                SongStatus::Stopped => 2,
+                SongStatus::NoTrack => 3,
+            };
+            let priority = config
+                .priority
+                .iter()
+                .position(|pattern| matches_name(pattern, &song.player, &song.identity))
+                .unwrap_or(config.priority.len());
+            (status_rank, priority)
+        });
+
+        let song = match (players.first(), error) {
+            (Some(song), _) => Ok(song.clone()),
+            (None, Some(e)) if source_count == 1 => Err(e),
+            (None, _) => Err(Error::NoPlayer),
+        };
+        let update = PlayerUpdate {
+            song,
+            players,
+            seeked: update.seeked,
+        };
+        if tx.send(update).is_err() {
+            return;
+        }
+    }
+}
+
+fn is_allowed(config: &PlayerConfig, song: &SongInfo) -> bool {
+    let matches = |pattern: &String| matches_name(pattern, &song.player, &song.identity);
+    (config.players.is_empty() || config.players.iter().any(matches))
+        && !config.blocklist.iter().any(matches)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::Receiver;
+    use std::time::Duration;
+
+    /// 转发测试推送的更新
+    struct FakeSource {
+        updates: Receiver<PlayerUpdate>,
+    }
+
+    impl PlayerSource for FakeSource {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn snapshot(&mut self) -> PlayerUpdate {
+            PlayerUpdate::none(Error::NoPlayer)
+        }
+
+        fn watch(&mut self, tx: &Sender<PlayerUpdate>) {
+            for update in &self.updates {
+                if tx.send(update).is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    /// 启动合并，返回每个来源的发送端和合并结果的接收端
+    fn start(
+        config: PlayerConfig,
+        count: usize,
+    ) -> (Vec<Sender<PlayerUpdate>>, Receiver<PlayerUpdate>) {
+        let (senders, sources): (Vec<_>, Vec<Box<dyn PlayerSource>>) = (0..count)
+            .map(|_| {
+                let (tx, updates) = mpsc::channel();
+                (
+                    tx,
+                    Box::new(FakeSource { updates }) as Box<dyn PlayerSource>,
+                )
+            })
+            .unzip();
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || watch_sources(&config, sources, tx));
+        (senders, rx)
+    }
+
+    fn song(player: &str, status: SongStatus, position: f64) -> SongInfo {
+        SongInfo {
+            id: format!("{}-song", player),
+            artist: "Artist".to_string(),
+            title: "Title".to_string(),
+            album: None,
+            album_artist: None,
+            length: Some(200.0),
+            track_id: None,
+            track_number: None,
+            art_url: None,
+            position,
+            rate: 1.0,
+            status,
+            url: None,
+            player: player.to_string(),
+            identity: player.to_string(),
+        }
+    }
+
+    fn players(update: &PlayerUpdate) -> Vec<&str> {
+        update.players.iter().map(|s| s.player.as_str()).collect()
+    }
+
+    fn next(rx: &Receiver<PlayerUpdate>) -> PlayerUpdate {
+        rx.recv_timeout(Duration::from_secs(5)).unwrap()
+    }
+
+    #[test]
+    fn priority_orders_across_sources() {
+        let config = PlayerConfig {
+            priority: vec!["mpv".to_string()],
+            ..PlayerConfig::default()
+        };
+        let (sources, rx) = start(config, 2);
+
+        let mpd = song("mpd", SongStatus::Playing, 0.0);
+        sources[0].send(PlayerUpdate::single(mpd, false)).unwrap();
+        assert_eq!(players(&next(&rx)), ["mpd"]);
+
+        let mpv = song("mpv", SongStatus::Playing, 0.0);
+        sources[1].send(PlayerUpdate::single(mpv, true)).unwrap();
+        let update = next(&rx);
+        assert_eq!(players(&update), ["mpv", "mpd"]);
+        assert_eq!(update.song.unwrap().player, "mpv");
+        assert_eq!(update.seeked.as_deref(), Some("mpv"));
+
+        // 正在播放优先于 `priority`
+        let mpv = song("mpv", SongStatus::Paused, 0.0);
+        sources[1].send(PlayerUpdate::single(mpv, false)).unwrap();
+        assert_eq!(players(&next(&rx)), ["mpd", "mpv"]);
+    }
+
+    #[test]
+    fn blocklist_applies_to_all_sources() {
+        let config = PlayerConfig {
+            blocklist: vec!["mpd".to_string()],
+            ..PlayerConfig::default()
+        };
+        let (sources, rx) = start(config, 1);
+
+        let mpd = song("mpd", SongStatus::Playing, 0.0);
+        sources[0].send(PlayerUpdate::single(mpd, false)).unwrap();
+        let update = next(&rx);
+        assert!(update.players.is_empty());
+        assert!(matches!(update.song, Err(Error::NoPlayer)));
+    }
+
+    #[test]
+    fn stale_positions_are_extrapolated() {
+        let (sources, rx) = start(PlayerConfig::default(), 2);
+
+        let playing = song("mpd", SongStatus::Playing, 10.0);
+        let paused = song("cmus", SongStatus::Paused, 10.0);
+        let update = PlayerUpdate {
+            song: Ok(playing.clone()),
+            players: vec![playing, paused],
+            seeked: None,
+        };
+        sources[0].send(update).unwrap();
+        next(&rx);
+
+        thread::sleep(Duration::from_millis(200));
+        let mpv = song("mpv", SongStatus::Stopped, 0.0);
+        sources[1].send(PlayerUpdate::single(mpv, false)).unwrap();
+        let update = next(&rx);
+        assert_eq!(players(&update), ["mpd", "cmus", "mpv"]);
+        assert!(update.players[0].position >= 10.2);
+        assert_eq!(update.players[1].position, 10.0);
+    }
+
+    #[test]
+    fn single_source_passes_errors_through() {
+        let (sources, rx) = start(PlayerConfig::default(), 1);
+        let error = Error::PlayerUnavailable("mpd".to_string());
+        sources[0].send(PlayerUpdate::none(error)).unwrap();
+        assert!(matches!(next(&rx).song, Err(Error::PlayerUnavailable(_))));
+    }
+
+    #[test]
+    fn multiple_sources_report_no_player() {
+        let (sources, rx) = start(PlayerConfig::default(), 2);
+        let error = Error::PlayerUnavailable("mpd".to_string());
+        sources[0].send(PlayerUpdate::none(error)).unwrap();
+        assert!(matches!(next(&rx).song, Err(Error::NoPlayer)));
+    }
+}
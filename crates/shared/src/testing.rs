@@ -0,0 +1,195 @@
+//! 测试用的本地 HTTP 服务器、歌曲、音频和临时文件
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use reqwest::Url;
+
+use crate::models::{SongInfo, SongStatus};
+
+/// 服务器收到的一个请求
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    /// 路径，不含查询参数
+    pub path: String,
+    query: Vec<(String, String)>,
+    headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Request {
+    pub fn query(&self, key: &str) -> Option<&str> {
+        find(&self.query, key)
+    }
+
+    /// 名称不区分大小写
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// `application/x-www-form-urlencoded` 请求体中的字段
+    pub fn form(&self, key: &str) -> Option<String> {
+        find(&decode_pairs(&self.body), key).map(str::to_string)
+    }
+}
+
+/// 本地 HTTP 服务器，`handler` 返回状态码和 JSON 响应体
+pub struct TestServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl TestServer {
+    pub fn start(handler: impl Fn(&Request) -> (u16, String) + Send + Sync + 'static) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler = Arc::new(handler);
+        let log = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let handler = handler.clone();
+                let log = log.clone();
+                thread::spawn(move || {
+                    serve(stream, |request| {
+                        log.lock().unwrap().push(request.clone());
+                        handler(request)
+                    })
+                });
+            }
+        });
+        Self { url, requests }
+    }
+
+    /// 到目前为止收到的请求
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// 收到的请求路径
+    pub fn paths(&self) -> Vec<String> {
+        self.requests().into_iter().map(|r| r.path).collect()
+    }
+}
+
+/// 处理一个连接上的所有请求（客户端会复用连接）
+fn serve(stream: TcpStream, handler: impl Fn(&Request) -> (u16, String)) {
+    let mut writer = stream.try_clone().unwrap();
+    let mut reader = BufReader::new(stream);
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+            return;
+        }
+        let mut parts = line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let target = parts.next().unwrap_or_default().to_string();
+
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                return;
+            }
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_string(), value.trim().to_string()));
+            }
+        }
+        let length = find(&headers, "content-length")
+            .and_then(|l| l.parse().ok())
+            .unwrap_or(0);
+        let mut body = vec![0; length];
+        if reader.read_exact(&mut body).is_err() {
+            return;
+        }
+
+        let url = Url::parse(&format!("http://localhost{}", target)).unwrap();
+        let request = Request {
+            method,
+            path: url.path().to_string(),
+            query: decode_pairs(url.query().unwrap_or_default()),
+            headers,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        };
+        let (status, body) = handler(&request);
+        let response = format!(
+            "HTTP/1.1 {} Test\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        );
+        if writer.write_all(response.as_bytes()).is_err() {
+            return;
+        }
+    }
+}
+
+fn find<'a>(pairs: &'a [(String, String)], key: &str) -> Option<&'a str> {
+    pairs
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(key))
+        .map(|(_, v)| v.as_str())
+}
+
+fn decode_pairs(encoded: &str) -> Vec<(String, String)> {
+    let url = Url::parse(&format!("http://localhost/?{}", encoded)).unwrap();
+    url.query_pairs().into_owned().collect()
+}
+
+/// 正在播放的歌曲
+pub fn song(artist: &str, title: &str) -> SongInfo {
+    SongInfo {
+        id: format!("{} - {}", artist, title),
+        artist: artist.to_string(),
+        title: title.to_string(),
+        album: None,
+        album_artist: None,
+        length: None,
+        track_id: None,
+        track_number: None,
+        art_url: None,
+        position: 0.0,
+        rate: 1.0,
+        status: SongStatus::Playing,
+        url: None,
+        player: "test".to_string(),
+        identity: "test".to_string(),
+    }
+}
+
+/// 8 kHz 单声道 8 位的静音 WAV，没有标签
+pub fn silent_wav(millis: usize) -> Vec<u8> {
+    let data = vec![0x80u8; 8 * millis];
+    let mut wav = Vec::new();
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data.len() as u32).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // PCM、单声道、采样率、字节率、块对齐、位深
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&8000u32.to_le_bytes());
+    wav.extend_from_slice(&8000u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&8u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    wav.extend_from_slice(&data);
+    wav
+}
+
+/// 本次测试进程专用的临时文件路径
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("bard-test-{}-{}", std::process::id(), name))
+}
@@ -1,147 +1,434 @@
-use anyhow::Result;
-use reqwest::Client;
+use anyhow::{Context, Result, bail};
+use reqwest::blocking::{Client, Response};
 use serde::{Deserialize, Serialize};
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::fs::{OpenOptions, Permissions};
+use std::io::Write;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::config::Config;
+use crate::http;
+
+const CLIENT_ID: &str = "49YxDN9a2aFV6RTG";
+const SCOPE: &str = "r_usr w_usr";
+
+/// Refresh the access token this many seconds before it expires
+const EXPIRY_MARGIN: u64 = 60;
+
+/// How much longer to wait between polls when the server asks us to slow down
+const SLOW_DOWN_STEP: Duration = if cfg!(test) {
+    Duration::from_millis(10)
+} else {
+    Duration::from_secs(5)
+};
+
+/// Login tokens, kept in their own file so refreshing never rewrites config.toml
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TidalTokens {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Unix timestamp (seconds) when the access token expires, 0 if unknown
+    pub expires_at: u64,
+}
+
+impl TidalTokens {
+    /// Reads the token file, `None` if it does not exist yet
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        toml::from_str(&text)
+            .map(Some)
+            .with_context(|| format!("Could not parse {}", path.display()))
+    }
+
+    /// Writes the token file, readable only by the user
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Could not create {}", dir.display()))?;
+        }
+        let text = toml::to_string_pretty(self).context("Could not serialize Tidal tokens")?;
+        let write = || -> std::io::Result<()> {
+            let mut file = OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(0o600)
+                .open(path)?;
+            // `mode` only applies when the file is created
+            file.set_permissions(Permissions::from_mode(0o600))?;
+            file.write_all(text.as_bytes())
+        };
+        write().with_context(|| format!("Could not write {}", path.display()))
+    }
+}
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TidalAuthResponse {
     pub access_token: String,
+    /// Only returned by the device login; refreshing keeps the existing one
+    pub refresh_token: Option<String>,
     pub expires_in: u64,
     pub token_type: String,
+    #[serde(default)]
     pub scope: String,
-    pub user_id: u64,
+    pub user_id: Option<u64>,
 }
 
+/// OAuth error body, e.g. `{"error": "authorization_pending"}`
+#[derive(Debug, Deserialize)]
+struct TidalAuthError {
+    error: String,
+    error_description: Option<String>,
+}
+
+impl std::fmt::Display for TidalAuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.error_description {
+            Some(description) => write!(f, "{}: {}", self.error, description),
+            None => write!(f, "{}", self.error),
+        }
+    }
+}
+
+#[derive(Clone)]
 pub struct TidalAuth {
     client: Client,
-    access_token: String,
-    refresh_token: String,
-    expires_at: u64,
+    auth_url: String,
+    tokens: TidalTokens,
+    /// Token file that refreshed tokens are written back to
+    token_path: PathBuf,
 }
 
 impl TidalAuth {
-    pub fn new(access_token: String, refresh_token: String) -> Self {
+    pub fn new(auth_url: &str, tokens: TidalTokens, token_path: PathBuf) -> Self {
         Self {
-            client: Client::new(),
-            access_token,
-            refresh_token,
-            expires_at: 0, // Will be set when we validate the token
+            client: http::client(),
+            auth_url: auth_url.trim_end_matches('/').to_string(),
+            tokens,
+            token_path,
         }
     }
 
-    /// Validates the current access token by making a request to /oauth2/me
-    /// Returns true if the token is valid, false otherwise
-    pub async fn validate_token(&self) -> Result<bool> {
-        let response = self
-            .client
-            .get("https://login.tidal.com/oauth2/me")
-            .header("Authorization", format!("Bearer {}", self.access_token))
-            .send()
-            .await?;
-
-        let status = response.status();
-
-        Ok(status.is_success())
-    }
-
-    /// Refreshes the access token using the refresh token
+    /// Refreshes the access token using the refresh token and saves it to the token file
     /// Returns the new access token if successful
-    pub async fn refresh_access_token(&mut self) -> Result<String> {
+    pub fn refresh_access_token(&mut self) -> Result<String> {
+        if self.tokens.refresh_token.is_empty() {
+            bail!("No Tidal refresh token, run `bard tidal login`");
+        }
         let form_data = [
-            ("client_id", "49YxDN9a2aFV6RTG"),
+            ("client_id", CLIENT_ID),
             ("grant_type", "refresh_token"),
-            ("refresh_token", &self.refresh_token),
-            ("scope", "r_usr+w_usr"),
+            ("refresh_token", &self.tokens.refresh_token),
+            ("scope", SCOPE),
         ];
-
         let response = self
             .client
-            .post("https://auth.tidal.com/v1/oauth2/token")
+            .post(format!("{}/v1/oauth2/token", self.auth_url))
             .form(&form_data)
-            .send()
-            .await?;
+            .send()?;
+        let auth_response = parse_token_response(response)
+            .map_err(|e| e.context("Failed to refresh Tidal token"))?;
 
-        let status = response.status();
-        if status.is_success() {
-            let response_text = response.text().await?;
-
-            let auth_response: TidalAuthResponse =
-                serde_json::from_str(&response_text).map_err(|e| {
-                    anyhow::anyhow!(
-                        "Failed to parse refresh response: {}. Response: {}",
-                        e,
-                        response_text
-                    )
-                })?;
-
-            let new_access_token = auth_response.access_token.clone();
-            self.access_token = new_access_token.clone();
-
-            // Save the updated access token to config
-            let mut config = Config::load()?;
-            if let Some(ref mut tidal) = config.tidal {
-                tidal.access_token = new_access_token.clone();
-                config.save()?;
-            }
+        self.tokens.access_token = auth_response.access_token.clone();
+        if let Some(refresh_token) = auth_response.refresh_token {
+            self.tokens.refresh_token = refresh_token;
+        }
+        self.tokens.expires_at = now() + auth_response.expires_in;
 
-            // Note: refresh_token doesn't change, so we keep the existing one
+        self.tokens.save(&self.token_path)?;
 
-            // Set expiration time (current time + expires_in seconds)
-            let current_time = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_secs();
-            self.expires_at = current_time + auth_response.expires_in;
-
-            Ok(new_access_token)
-        } else {
-            let error_text = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
-            Err(anyhow::anyhow!(
-                "Failed to refresh token: {} - {}",
-                status,
-                error_text
-            ))
+        Ok(auth_response.access_token)
+    }
+
+    /// Ensures we have an access token that has not expired, refreshing if necessary
+    pub fn ensure_valid_token(&mut self) -> Result<()> {
+        let expired =
+            self.tokens.expires_at != 0 && now() + EXPIRY_MARGIN >= self.tokens.expires_at;
+        if self.tokens.access_token.is_empty() || expired {
+            self.refresh_access_token()?;
         }
+        Ok(())
     }
 
-    /// Ensures we have a valid access token, refreshing if necessary
-    pub async fn ensure_valid_token(&mut self) -> Result<()> {
-        // First try to validate the current token
-        if self.validate_token().await? {
-            return Ok(());
+    /// The current tokens, possibly expired
+    pub fn tokens(&self) -> &TidalTokens {
+        &self.tokens
+    }
+
+    /// Gets the current access token, ensuring it's valid first
+    pub fn get_valid_access_token(&mut self) -> Result<String> {
+        self.ensure_valid_token()?;
+        Ok(self.tokens.access_token.clone())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeviceAuthorization {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    verification_uri_complete: Option<String>,
+    expires_in: u64,
+    interval: u64,
+}
+
+/// OAuth device login: the user opens a link on any device and enters the code
+pub struct DeviceLogin {
+    client: Client,
+    auth_url: String,
+    device_code: String,
+    /// Code the user enters at `verification_uri`
+    pub user_code: String,
+    /// Link to open, with the code already filled in when the server provides one
+    pub verification_uri: String,
+    interval: Duration,
+    expires_in: Duration,
+}
+
+impl DeviceLogin {
+    pub fn start(auth_url: &str) -> Result<Self> {
+        let client = http::client();
+        let auth_url = auth_url.trim_end_matches('/').to_string();
+        let response = client
+            .post(format!("{}/v1/oauth2/device_authorization", auth_url))
+            .form(&[("client_id", CLIENT_ID), ("scope", SCOPE)])
+            .send()?;
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().unwrap_or_default();
+            bail!("Failed to start Tidal login: {} - {}", status, error_text);
         }
+        let device: DeviceAuthorization = response
+            .json()
+            .context("Failed to parse device authorization")?;
+
+        let verification_uri = device
+            .verification_uri_complete
+            .unwrap_or(device.verification_uri);
+        Ok(Self {
+            client,
+            auth_url,
+            device_code: device.device_code,
+            user_code: device.user_code,
+            verification_uri: if verification_uri.contains("://") {
+                verification_uri
+            } else {
+                format!("https://{}", verification_uri)
+            },
+            interval: Duration::from_secs(device.interval.max(1)),
+            expires_in: Duration::from_secs(device.expires_in),
+        })
+    }
 
-        // If validation fails, try to refresh
-        if !self.refresh_token.is_empty() {
-            self.refresh_access_token().await?;
-        } else {
-            return Err(anyhow::anyhow!(
-                "No refresh token available and access token is invalid"
-            ));
+    /// Polls until the user authorizes this device, returns the new tokens
+    pub fn wait(&self) -> Result<TidalTokens> {
+        let deadline = Instant::now() + self.expires_in;
+        let mut interval = self.interval;
+        while Instant::now() < deadline {
+            thread::sleep(interval);
+            let form_data = [
+                ("client_id", CLIENT_ID),
+                ("device_code", &self.device_code),
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                ("scope", SCOPE),
+            ];
+            let response = self
+                .client
+                .post(format!("{}/v1/oauth2/token", self.auth_url))
+                .form(&form_data)
+                .send()?;
+            let status = response.status();
+            if status.is_success() {
+                let auth_response = parse_token_response(response)?;
+                return Ok(TidalTokens {
+                    access_token: auth_response.access_token,
+                    refresh_token: auth_response.refresh_token.unwrap_or_default(),
+                    expires_at: now() + auth_response.expires_in,
+                });
+            }
+            let error_text = response.text().unwrap_or_default();
+            match serde_json::from_str::<TidalAuthError>(&error_text) {
+                // The user has not entered the code yet
+                Ok(error) if error.error == "authorization_pending" => {}
+                Ok(error) if error.error == "slow_down" => interval += SLOW_DOWN_STEP,
+                Ok(error) => bail!("Tidal login failed: {}", error),
+                Err(_) => bail!("Tidal login failed: {} - {}", status, error_text),
+            }
         }
+        bail!("Tidal login timed out")
+    }
+}
 
-        Ok(())
+/// Parses a token response, with the OAuth error message when the request failed
+fn parse_token_response(response: Response) -> Result<TidalAuthResponse> {
+    let status = response.status();
+    let response_text = response.text()?;
+    if status.is_success() {
+        return serde_json::from_str(&response_text).map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to parse token response: {}. Response: {}",
+                e,
+                response_text
+            )
+        });
+    }
+    match serde_json::from_str::<TidalAuthError>(&response_text) {
+        Ok(error) => Err(anyhow::anyhow!("{}", error)),
+        Err(_) => Err(anyhow::anyhow!("{} - {}", status, response_text)),
     }
+}
 
-    /// Gets the current access token, ensuring it's valid first
-    pub async fn get_valid_access_token(&mut self) -> Result<String> {
-        self.ensure_valid_token().await?;
-        Ok(self.access_token.clone())
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{TestServer, temp_path};
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn token_response(access_token: &str, refresh_token: Option<&str>) -> String {
+        json!({
+            "access_token": access_token,
+            "refresh_token": refresh_token,
+            "expires_in": 3600,
+            "token_type": "Bearer",
+        })
+        .to_string()
     }
 
-    /// Gets the current refresh token
-    pub fn get_refresh_token(&self) -> &str {
-        &self.refresh_token
+    #[test]
+    fn device_login_waits_for_authorization() {
+        let polls = AtomicUsize::new(0);
+        let server = TestServer::start(move |request| match request.path.as_str() {
+            "/v1/oauth2/device_authorization" => (
+                200,
+                json!({
+                    "deviceCode": "device",
+                    "userCode": "ABCDE",
+                    "verificationUri": "link.tidal.com",
+                    "verificationUriComplete": "link.tidal.com/ABCDE",
+                    "expiresIn": 300,
+                    "interval": 5,
+                })
+                .to_string(),
+            ),
+            "/v1/oauth2/token" => match polls.fetch_add(1, Ordering::SeqCst) {
+                0 => (400, json!({ "error": "authorization_pending" }).to_string()),
+                1 => (400, json!({ "error": "slow_down" }).to_string()),
+                _ => (200, token_response("access", Some("refresh"))),
+            },
+            _ => (404, String::new()),
+        });
+
+        let mut login = DeviceLogin::start(&server.url).unwrap();
+        assert_eq!(login.user_code, "ABCDE");
+        assert_eq!(login.verification_uri, "https://link.tidal.com/ABCDE");
+        assert_eq!(login.interval, Duration::from_secs(5));
+        login.interval = Duration::from_millis(10);
+        let tokens = login.wait().unwrap();
+        assert_eq!(tokens.access_token, "access");
+        assert_eq!(tokens.refresh_token, "refresh");
+        assert!(tokens.expires_at >= now() + 3590);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[0].form("client_id").as_deref(), Some(CLIENT_ID));
+        assert!(
+            requests
+                .iter()
+                .all(|r| r.header("user-agent").unwrap().starts_with("bard/"))
+        );
+        for request in &requests[1..] {
+            assert_eq!(request.form("device_code").as_deref(), Some("device"));
+            assert_eq!(
+                request.form("grant_type").as_deref(),
+                Some("urn:ietf:params:oauth:grant-type:device_code")
+            );
+        }
+    }
+
+    #[test]
+    fn device_login_reports_errors() {
+        let server = TestServer::start(|request| match request.path.as_str() {
+            "/v1/oauth2/device_authorization" => (
+                200,
+                json!({
+                    "deviceCode": "device",
+                    "userCode": "ABCDE",
+                    "verificationUri": "https://link.tidal.com",
+                    "expiresIn": 300,
+                    "interval": 1,
+                })
+                .to_string(),
+            ),
+            _ => (
+                400,
+                json!({ "error": "expired_token", "error_description": "Code expired" })
+                    .to_string(),
+            ),
+        });
+        let mut login = DeviceLogin::start(&server.url).unwrap();
+        assert_eq!(login.verification_uri, "https://link.tidal.com");
+        login.interval = Duration::from_millis(10);
+        let error = login.wait().unwrap_err().to_string();
+        assert!(error.contains("expired_token: Code expired"), "{}", error);
+    }
+
+    #[test]
+    fn expired_token_is_refreshed_and_saved() {
+        let server = TestServer::start(|_| (200, token_response("new", None)));
+        let path = temp_path("tidal-refresh.toml");
+        let tokens = TidalTokens {
+            access_token: "old".to_string(),
+            refresh_token: "refresh".to_string(),
+            expires_at: now() + EXPIRY_MARGIN / 2,
+        };
+        let mut auth = TidalAuth::new(&server.url, tokens, path.clone());
+        assert_eq!(auth.get_valid_access_token().unwrap(), "new");
+        // No refresh while the token has not expired
+        assert_eq!(auth.get_valid_access_token().unwrap(), "new");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/v1/oauth2/token");
+        assert_eq!(
+            requests[0].form("grant_type").as_deref(),
+            Some("refresh_token")
+        );
+        assert_eq!(
+            requests[0].form("refresh_token").as_deref(),
+            Some("refresh")
+        );
+
+        // The refresh response has no refresh token, so the old one is kept
+        let saved = TidalTokens::load(&path).unwrap().unwrap();
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(saved.access_token, "new");
+        assert_eq!(saved.refresh_token, "refresh");
+        assert!(saved.expires_at >= now() + 3590);
+        assert_eq!(mode & 0o777, 0o600);
     }
 
-    /// Updates the tokens (useful after a successful refresh)
-    pub fn update_tokens(&mut self, access_token: String, refresh_token: String) {
-        self.access_token = access_token;
-        self.refresh_token = refresh_token;
+    #[test]
+    fn missing_token_file() {
+        assert!(
+            TidalTokens::load(&temp_path("tidal-missing.toml"))
+                .unwrap()
+                .is_none()
+        );
     }
 }
@@ -0,0 +1,210 @@
+use anyhow::Context;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStringExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+
+/// 将播放器给出的 `xesam:url` 解析为本地文件路径
+///
+/// 支持百分号编码（按原始字节还原，不要求 UTF-8）、`file://localhost/`、
+/// 本机主机名以及 Flatpak 文档门户 `/run/user/*/doc/` 下的路径。
+/// 非本地文件（http、spotify 等）返回说明原因的错误。
+pub fn resolve_local_path(url: &str) -> Result<PathBuf> {
+    // 部分播放器直接给出路径
+    if url.starts_with('/') {
+        return Ok(resolve_document_portal(PathBuf::from(url)));
+    }
+
+    let Some((scheme, rest)) = url.split_once(':') else {
+        return Err(unsupported(format!("not a URL or absolute path: {}", url)));
+    };
+    if !scheme.eq_ignore_ascii_case("file") {
+        return Err(unsupported(format!(
+            "scheme '{}' is not a local file: {}",
+            scheme, url
+        )));
+    }
+
+    // file:///path、file://host/path 或 file:/path
+    let path = match rest.strip_prefix("//") {
+        Some(authority_and_path) => {
+            let (host, path) = match authority_and_path.find('/') {
+                Some(i) => authority_and_path.split_at(i),
+                None => return Err(unsupported(format!("file URL has no path: {}", url))),
+            };
+            if !is_local_host(host) {
+                return Err(unsupported(format!(
+                    "file URL points to remote host '{}': {}",
+                    host, url
+                )));
+            }
+            path
+        }
+        None => rest,
+    };
+    if !path.starts_with('/') {
+        return Err(unsupported(format!(
+            "file URL has no absolute path: {}",
+            url
+        )));
+    }
+
+    let bytes = percent_decode(path)
+        .ok_or_else(|| unsupported(format!("malformed percent escape in {}", url)))?;
+    Ok(resolve_document_portal(PathBuf::from(OsString::from_vec(
+        bytes,
+    ))))
+}
+
+fn is_local_host(host: &str) -> bool {
+    if host.is_empty() || host.eq_ignore_ascii_case("localhost") {
+        return true;
+    }
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .is_ok_and(|hostname| hostname.trim().eq_ignore_ascii_case(host))
+}
+
+fn unsupported(message: String) -> Error {
+    Error::UnsupportedUrl(message)
+}
+
+/// 百分号解码为原始字节
+fn percent_decode(input: &str) -> Option<Vec<u8>> {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = bytes
+                .get(i + 1..i + 3)
+                .and_then(|h| std::str::from_utf8(h).ok())
+                .and_then(|h| u8::from_str_radix(h, 16).ok())?;
+            decoded.push(hex);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Some(decoded)
+}
+
+/// Flatpak 应用看到的是文档门户挂载的路径，查询门户得到宿主机上的真实路径，
+/// 这样才能找到同目录下的外置歌词。查询失败时保留原路径（仍可读取内嵌标签）。
+fn resolve_document_portal(path: PathBuf) -> PathBuf {
+    let Some(doc_id) = document_portal_id(&path) else {
+        return path;
+    };
+    match query_document_portal(&doc_id) {
+        Ok(host_path) => host_path,
+        Err(e) => {
+            eprintln!("bard: {:#}, using {}", e, path.display());
+            path
+        }
+    }
+}
+
+/// 匹配 `/run/user/<uid>/doc/<doc_id>/<name>`
+fn document_portal_id(path: &Path) -> Option<String> {
+    let rest = path.strip_prefix("/run/user").ok()?;
+    let mut components = rest.components();
+    components.next()?; // uid
+    if components.next()?.as_os_str() != "doc" {
+        return None;
+    }
+    let doc_id = components.next()?.as_os_str().to_str()?.to_string();
+    components.next()?; // file name
+    Some(doc_id)
+}
+
+fn query_document_portal(doc_id: &str) -> anyhow::Result<PathBuf> {
+    let conn = dbus::blocking::Connection::new_session().context("Could not connect to D-Bus")?;
+    let proxy = conn.with_proxy(
+        "org.freedesktop.portal.Documents",
+        "/org/freedesktop/portal/documents",
+        Duration::from_millis(500),
+    );
+    let (mut path, _apps): (Vec<u8>, HashMap<String, Vec<String>>) = proxy
+        .method_call("org.freedesktop.portal.Documents", "Info", (doc_id,))
+        .with_context(|| format!("Could not resolve document portal id {}", doc_id))?;
+    // 门户返回以 NUL 结尾的字节串
+    if path.last() == Some(&0) {
+        path.pop();
+    }
+    Ok(PathBuf::from(OsString::from_vec(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::ffi::OsStrExt;
+
+    #[test]
+    fn percent_escapes_decode_to_bytes() {
+        assert_eq!(percent_decode("/a%20b").unwrap(), b"/a b");
+        // Latin-1 的 `é`，不是 UTF-8
+        assert_eq!(percent_decode("/caf%E9").unwrap(), b"/caf\xe9");
+        assert_eq!(percent_decode("/%e4%bd%a0").unwrap(), "/你".as_bytes());
+        assert_eq!(percent_decode("/bad%2"), None);
+        assert_eq!(percent_decode("/bad%zz"), None);
+    }
+
+    #[test]
+    fn local_file_urls() {
+        let path = |url| resolve_local_path(url).unwrap();
+        assert_eq!(
+            path("file:///music/My%20Song.flac"),
+            PathBuf::from("/music/My Song.flac")
+        );
+        assert_eq!(
+            path("file://localhost/music/a.flac"),
+            PathBuf::from("/music/a.flac")
+        );
+        assert_eq!(path("file:/music/a.flac"), PathBuf::from("/music/a.flac"));
+        assert_eq!(path("/music/a b.flac"), PathBuf::from("/music/a b.flac"));
+    }
+
+    #[test]
+    fn non_utf8_path_is_kept() {
+        let path = resolve_local_path("file:///music/caf%E9.flac").unwrap();
+        assert_eq!(path.as_os_str().as_bytes(), b"/music/caf\xe9.flac");
+    }
+
+    #[test]
+    fn own_hostname_is_local() {
+        let Ok(hostname) = std::fs::read_to_string("/proc/sys/kernel/hostname") else {
+            return;
+        };
+        let url = format!("file://{}/music/a.flac", hostname.trim());
+        assert_eq!(
+            resolve_local_path(&url).unwrap(),
+            PathBuf::from("/music/a.flac")
+        );
+    }
+
+    #[test]
+    fn remote_and_non_file_urls_are_rejected() {
+        let unsupported = |url| matches!(resolve_local_path(url), Err(Error::UnsupportedUrl(_)));
+        assert!(unsupported("file://nas.invalid/music/a.flac"));
+        assert!(unsupported("https://example.com/a.flac"));
+        assert!(unsupported("spotify:track:123"));
+        assert!(unsupported("file://localhost"));
+        assert!(unsupported("relative/a.flac"));
+        assert!(unsupported("file:///bad%2"));
+    }
+
+    #[test]
+    fn document_portal_paths() {
+        let id = |path: &str| document_portal_id(Path::new(path));
+        assert_eq!(
+            id("/run/user/1000/doc/abc123/song.flac").as_deref(),
+            Some("abc123")
+        );
+        assert_eq!(id("/run/user/1000/doc/abc123"), None);
+        assert_eq!(id("/run/user/1000/other/abc123/song.flac"), None);
+        assert_eq!(id("/music/song.flac"), None);
+    }
+}
@@ -0,0 +1,79 @@
+use anyhow::{Result, bail};
+
+const USAGE: &str = "usage: waybar-bard [--player <pattern>[,<pattern>...]]";
+
+/// 命令行参数，覆盖配置文件中的对应项
+#[derive(Debug, Default)]
+pub struct Args {
+    /// `--player`：只使用匹配的播放器
+    pub players: Option<Vec<String>>,
+}
+
+impl Args {
+    pub fn parse() -> Result<Self> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut parsed = Args::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let value = match arg.split_once('=') {
+                Some(("--player", value)) => value.to_string(),
+                _ if arg == "--player" || arg == "-p" => match args.next() {
+                    Some(value) => value,
+                    None => bail!("--player requires a value\n{}", USAGE),
+                },
+                _ if arg == "--help" || arg == "-h" => {
+                    println!("{}", USAGE);
+                    std::process::exit(0);
+                }
+                _ => bail!("unknown argument `{}`\n{}", arg, USAGE),
+            };
+            // 可以多次指定，也可以用逗号分隔
+            parsed.players.get_or_insert_with(Vec::new).extend(
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(String::from),
+            );
+        }
+        Ok(parsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args> {
+        Args::parse_from(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_keep_config() {
+        assert_eq!(parse(&[]).unwrap().players, None);
+    }
+
+    #[test]
+    fn player_forms() {
+        let players = |args: &[&str]| parse(args).unwrap().players.unwrap();
+        assert_eq!(players(&["-p", "mpv"]), ["mpv"]);
+        assert_eq!(players(&["--player", "mpv"]), ["mpv"]);
+        assert_eq!(players(&["--player=mpv, spotify,"]), ["mpv", "spotify"]);
+        assert_eq!(players(&["-p", "mpv", "--player=vlc"]), ["mpv", "vlc"]);
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let error = parse(&["--player"]).unwrap_err().to_string();
+        assert!(error.starts_with("--player requires a value"), "{}", error);
+        let error = parse(&["--verbose"]).unwrap_err().to_string();
+        assert!(
+            error.starts_with("unknown argument `--verbose`"),
+            "{}",
+            error
+        );
+    }
+}
@@ -1,28 +1,69 @@
 use anyhow::Result;
-use shared::lyrics::{get_lyrics, get_lyrics_status};
-use shared::models::{LyricLine, SongInfo, SongStatus};
-use shared::player;
-use signal_hook::{consts::SIGUSR1, iterator::Signals};
+use shared::Error;
+use shared::clock::PlaybackClock;
+use shared::config::{Config, DisplayMode, WaybarConfig};
+use shared::lyrics::{LyricsPipeline, get_lyrics_status};
+use shared::models::{Lyrics, LyricsStatus, SongInfo, SongStatus};
+use shared::player::{self, TrackChange, TrackTracker};
+use signal_hook::{
+    consts::{SIGUSR1, SIGUSR2},
+    iterator::Signals,
+};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, RwLock, mpsc};
 use std::thread;
 use std::time::{Duration, Instant};
 
+use crate::models::WaybarOutput;
+
+mod cli;
 mod models;
 mod waybar;
 
+/// 单个播放器的歌词状态
+struct PlayerState {
+    song: SongInfo,
+    lyrics: Option<Lyrics>,
+    clock: PlaybackClock,
+    /// 判断切歌和重新播放
+    tracker: TrackTracker,
+    /// 开始暂停的时间
+    paused_since: Option<Instant>,
+}
+
 struct AppState {
-    song: Option<SongInfo>,
-    lyrics: Option<Vec<LyricLine>>,
-    last_update_time: Instant,
+    /// 跟踪的播放器，按选择顺序排列；`single` 模式下只有选中的一个
+    players: Vec<PlayerState>,
+    /// 播放器查询失败的原因
+    error: Option<String>,
+    /// `cycle` 模式下当前显示的播放器
+    pinned: Option<String>,
 }
 
 fn main() -> Result<()> {
+    let args = cli::Args::parse()?;
+    let mut config = Config::load().unwrap_or_else(|e| {
+        eprintln!("waybar-bard: {:#}, using defaults", e);
+        Config::default()
+    });
+    if let Some(players) = args.players {
+        config.player.players = players;
+        // 显式指定的播放器不受默认黑名单影响
+        config
+            .player
+            .blocklist
+            .retain(|blocked| !config.player.players.contains(blocked));
+    }
+    let player_config = config.player;
+    let waybar_config = config.waybar;
+    let mode = waybar_config.mode;
+    let lyrics_pipeline = LyricsPipeline::from_config(&config.lyrics);
+
     // Shared state between fetcher and renderer
     let state = Arc::new(RwLock::new(AppState {
-        song: None,
-        lyrics: None,
-        last_update_time: Instant::now(),
+        players: Vec::new(),
+        error: None,
+        pinned: None,
     }));
 
     // State to track if output should be hidden
@@ -30,9 +71,18 @@ fn main() -> Result<()> {
 
     // --- Signal Handler Thread ---
     let hidden_clone = hidden.clone();
+    let state_cycler = state.clone();
     thread::spawn(move || {
-        let mut signals = Signals::new([SIGUSR1]).expect("Failed to register signal handler");
-        for _sig in signals.forever() {
+        let mut signals =
+            Signals::new([SIGUSR1, SIGUSR2]).expect("Failed to register signal handler");
+        for sig in signals.forever() {
+            if sig == SIGUSR2 {
+                // 切换到下一个播放器
+                if let Ok(mut writer) = state_cycler.write() {
+                    writer.pinned = next_player(&writer);
+                }
+                continue;
+            }
             let current = hidden_clone.load(Ordering::Relaxed);
             hidden_clone.store(!current, Ordering::Relaxed);
             eprintln!("waybar-bard: Toggled hidden state to {}", !current);
@@ -40,61 +90,115 @@ fn main() -> Result<()> {
         }
     });
 
-    // --- Data Fetcher Thread (Background) ---
-    // Handles slow I/O: DBus and File Reading
+    // --- Player Watcher Thread (Background) ---
+    // Pushes song snapshots on MPRIS signals, polling only as a fallback
+    let (update_tx, update_rx) = mpsc::channel();
+    thread::spawn(move || player::watch(player_config, update_tx));
+
+    // --- Lyrics Worker Thread (Background) ---
+    // Handles slow I/O: lyrics files and online providers
+    let (lyrics_tx, lyrics_rx) = mpsc::channel::<SongInfo>();
+    let state_lyrics = state.clone();
+    thread::spawn(move || {
+        // 播放器仍在播放这一首时才使用结果
+        let is_current = |song: &SongInfo| {
+            state_lyrics.read().is_ok_and(|reader| {
+                reader
+                    .players
+                    .iter()
+                    .any(|p| p.song.player == song.player && p.song.id == song.id)
+            })
+        };
+        for song in lyrics_rx {
+            // 排队期间已经切歌的不再查询
+            if !is_current(&song) {
+                continue;
+            }
+            let lyrics = match lyrics_pipeline.fetch(&song) {
+                Ok(lyrics) => Some(lyrics),
+                Err(Error::NoLyrics(_)) => None,
+                Err(e) => {
+                    eprintln!("waybar-bard: {}", e);
+                    None
+                }
+            };
+            if let Ok(mut writer) = state_lyrics.write()
+                && let Some(player_state) = writer
+                    .players
+                    .iter_mut()
+                    .find(|p| p.song.player == song.player && p.song.id == song.id)
+            {
+                player_state.lyrics = lyrics;
+            }
+        }
+    });
+
+    // --- Player Update Thread (Background) ---
+    // Applies player updates right away, lyrics are loaded by the worker
     let state_updater = state.clone();
     thread::spawn(move || {
-        let mut last_song_id = String::new();
-        let mut poll_interval;
-
-        loop {
-            let loop_start = Instant::now();
-
-            match player::get_current_song() {
-                Ok(Some(song)) => {
-                    let mut new_lyrics = None;
-                    poll_interval = Duration::from_secs(1); // Active mode
-
-                    // Only fetch lyrics if song changed
-                    if song.id != last_song_id {
-                        match get_lyrics(&song) {
-                            Some(lyrics) => {
-                                new_lyrics = Some(lyrics);
-                            }
-                            None => {
-                                new_lyrics = None;
-                            }
-                        }
-                        last_song_id = song.id.clone();
-                    }
+        for update in update_rx {
+            // single 模式只跟踪选中的播放器，其余模式跟踪全部
+            let (songs, error) = match (mode, update.song) {
+                (DisplayMode::Single, Ok(song)) => (vec![song], None),
+                (_, Ok(_)) | (_, Err(Error::NoPlayer)) => (update.players, None),
+                (DisplayMode::Single, Err(e)) => (Vec::new(), Some(e)),
+                (_, Err(e)) if update.players.is_empty() => (Vec::new(), Some(e)),
+                (_, Err(_)) => (update.players, None),
+            };
+            if let Some(e) = &error {
+                eprintln!("Error getting song info: {}", e);
+            }
 
-                    // Update shared state
-                    if let Ok(mut writer) = state_updater.write() {
-                        writer.song = Some(song);
-                        writer.last_update_time = Instant::now();
-                        if new_lyrics.is_some() {
-                            writer.lyrics = new_lyrics;
+            let mut requests = Vec::new();
+            if let Ok(mut writer) = state_updater.write() {
+                let mut previous = std::mem::take(&mut writer.players);
+                for song in songs {
+                    let index = previous.iter().position(|p| p.song.player == song.player);
+                    let (mut clock, lyrics, mut tracker, paused_since) =
+                        match index.map(|i| previous.swap_remove(i)) {
+                            Some(old) => (old.clock, old.lyrics, old.tracker, old.paused_since),
+                            None => (PlaybackClock::new(), None, TrackTracker::new(), None),
+                        };
+                    let paused_since = match song.status {
+                        SongStatus::Paused => paused_since.or(Some(Instant::now())),
+                        _ => None,
+                    };
+                    let change = if song.status == SongStatus::NoTrack {
+                        tracker = TrackTracker::new();
+                        TrackChange::New
+                    } else {
+                        let change = tracker.update(&song, &clock);
+                        // 重新播放时也重新读取，歌词文件可能已被修改
+                        if change != TrackChange::Same {
+                            requests.push(song.clone());
                         }
+                        change
+                    };
+                    // 换歌或重新播放时把进度直接对齐，换歌时先清除旧歌词
+                    let seeked = update.seeked.as_deref() == Some(song.player.as_str());
+                    if seeked || change != TrackChange::Same {
+                        clock.seek(song.position);
                     }
+                    let lyrics = if change == TrackChange::New {
+                        None
+                    } else {
+                        lyrics
+                    };
+                    clock.update(song.position, song.rate, song.status == SongStatus::Playing);
+                    writer.players.push(PlayerState {
+                        song,
+                        lyrics,
+                        clock,
+                        tracker,
+                        paused_since,
+                    });
                 }
-                Ok(None) => {
-                    if let Ok(mut writer) = state_updater.write() {
-                        writer.song = None;
-                        writer.lyrics = None;
-                    }
-                    last_song_id.clear();
-                    poll_interval = Duration::from_secs(5); // Idle mode: no player found
-                }
-                Err(e) => {
-                    eprintln!("Error getting song info: {}", e);
-                    poll_interval = Duration::from_secs(2); // Error recovery mode
-                }
+                writer.error = error.map(|e| e.to_string());
             }
-
-            // Adaptive sleep
-            let elapsed = loop_start.elapsed();
-            if elapsed < poll_interval {
-                thread::sleep(poll_interval - elapsed);
+            // 状态更新后再请求，工作线程据此判断是否已经切歌
+            for song in requests {
+                let _ = lyrics_tx.send(song);
             }
         }
     });
@@ -104,7 +208,7 @@ fn main() -> Result<()> {
     loop {
         // 1. Check hidden state
         if hidden.load(Ordering::Relaxed) {
-            waybar::render_empty();
+            waybar::print(&waybar::render_empty());
             thread::sleep(Duration::from_millis(500));
             continue;
         }
@@ -114,58 +218,151 @@ fn main() -> Result<()> {
         // 2. Read state and render
         // Use a read lock, which is fast and allows multiple readers if needed
         if let Ok(reader) = state.read() {
-            match &reader.song {
-                Some(song) => {
-                    if song.status == SongStatus::Paused {
-                        waybar::render_song_info(song);
-                        sleep_duration = Duration::from_millis(500);
-                    } else {
-                        // Interpolate position: DBus Position + Time since DBus update
-                        let elapsed_since_update = reader.last_update_time.elapsed().as_secs_f64();
-                        let current_position = song.position + elapsed_since_update;
-
-                        match &reader.lyrics {
-                            Some(lyrics_data) => {
-                                let current_lyric =
-                                    get_lyrics_status(lyrics_data, current_position);
-
-                                let display_text = &current_lyric.current_line.text;
-                                let next_text = current_lyric
-                                    .current_line
-                                    .translation
-                                    .clone()
-                                    .unwrap_or_else(|| current_lyric.next_line.clone());
-
-                                waybar::render_lyrics(display_text, next_text, String::new());
-
-                                // Calculate dynamic sleep to sync with next line
-                                if let Some(next_ts) = current_lyric.next_timestamp {
-                                    let time_until_next = next_ts - current_position;
-                                    if time_until_next > 0.0 {
-                                        // Sleep until next line, but cap at 0.5s for responsiveness
-                                        // And floor at 0.05s to avoid busy looping
-                                        sleep_duration = Duration::from_secs_f64(
-                                            time_until_next.clamp(0.05, 0.5),
-                                        );
-                                    } else {
-                                        sleep_duration = Duration::from_millis(50);
-                                    }
-                                }
-                            }
-                            None => {
-                                waybar::render_song_info(song);
-                                sleep_duration = Duration::from_secs(1);
-                            }
-                        }
-                    }
+            let shown: Vec<&PlayerState> = match mode {
+                DisplayMode::All => reader.players.iter().collect(),
+                DisplayMode::Cycle => reader
+                    .players
+                    .iter()
+                    .find(|p| Some(&p.song.player) == reader.pinned.as_ref())
+                    .or(reader.players.first())
+                    .into_iter()
+                    .collect(),
+                DisplayMode::Single => reader.players.first().into_iter().collect(),
+            };
+
+            if shown.is_empty() {
+                match &reader.error {
+                    Some(error) => waybar::print(&waybar::render_error(error)),
+                    None => waybar::print(&waybar::render_no_song()),
                 }
-                None => {
-                    waybar::render_no_song();
-                    sleep_duration = Duration::from_secs(1);
+                sleep_duration = Duration::from_secs(1);
+            } else {
+                let mut outputs = Vec::new();
+                sleep_duration = Duration::from_secs(1);
+                for player_state in shown {
+                    let (mut output, sleep) = render_player(player_state, &waybar_config);
+                    output.class.push(player_class(&player_state.song.player));
+                    outputs.push(output);
+                    sleep_duration = sleep_duration.min(sleep);
                 }
+                let output = match outputs.len() {
+                    1 => outputs.remove(0),
+                    _ => waybar::render_combined(outputs, &waybar_config.separator),
+                };
+                waybar::print(&output);
             }
         }
 
         thread::sleep(sleep_duration);
     }
 }
+
+/// 渲染一个播放器，返回输出和距离下一次需要刷新的时间
+fn render_player(player_state: &PlayerState, config: &WaybarConfig) -> (WaybarOutput, Duration) {
+    let song = &player_state.song;
+    match song.status {
+        SongStatus::Playing => {}
+        SongStatus::Paused => {
+            let hide_after = Duration::from_secs(config.hide_paused_after * 60);
+            let idle = config.hide_paused_after > 0
+                && player_state
+                    .paused_since
+                    .is_some_and(|since| since.elapsed() >= hide_after);
+            return (
+                waybar::render_paused(song, idle),
+                Duration::from_millis(500),
+            );
+        }
+        SongStatus::Stopped => return (waybar::render_stopped(), Duration::from_secs(1)),
+        SongStatus::NoTrack => return (waybar::render_no_track(), Duration::from_secs(1)),
+    }
+    let karaoke = &config.karaoke;
+
+    let Some(lyrics_data) = &player_state.lyrics else {
+        return (waybar::render_song_info(song), Duration::from_secs(1));
+    };
+
+    // Interpolate position from the last player update
+    let current_position = player_state.clock.now();
+    let current_lyric = get_lyrics_status(&lyrics_data.lines, current_position);
+
+    let display_text = &current_lyric.current_line.text;
+    let next_text = current_lyric
+        .current_line
+        .translation
+        .clone()
+        .unwrap_or_else(|| current_lyric.next_line.clone());
+
+    let karaoke_active = karaoke.enabled && current_lyric.current_line.words.is_some();
+    let output = if karaoke_active {
+        waybar::render_karaoke(&current_lyric, next_text, String::new(), karaoke)
+    } else {
+        waybar::render_lyrics(display_text, next_text, String::new())
+    };
+
+    // Calculate dynamic sleep to sync with next line (or next word)
+    let mut sleep_duration = Duration::from_millis(200);
+    let mut next_ts = current_lyric.next_timestamp;
+    if karaoke_active {
+        next_ts = earliest(next_ts, current_lyric.next_word_timestamp);
+        next_ts = earliest(
+            next_ts,
+            karaoke_step(&current_lyric).map(|step| current_position + step),
+        );
+    }
+    if let Some(next_ts) = next_ts {
+        let time_until_next = next_ts - current_position;
+        if time_until_next > 0.0 {
+            // Sleep until next line, but cap at 0.5s for responsiveness
+            // And floor at 0.05s to avoid busy looping
+            sleep_duration = Duration::from_secs_f64(time_until_next.clamp(0.05, 0.5));
+        } else {
+            sleep_duration = Duration::from_millis(50);
+        }
+    }
+    (output, sleep_duration)
+}
+
+/// 播放器对应的 CSS 类名，例如 `org.mpris.MediaPlayer2.firefox.instance_1_2` 为 `player-firefox`
+fn player_class(player: &str) -> String {
+    let base = player.split('.').next().unwrap_or(player);
+    let name: String = base
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    format!("player-{}", name)
+}
+
+/// `cycle` 模式下的下一个播放器
+fn next_player(state: &AppState) -> Option<String> {
+    let names: Vec<&String> = state.players.iter().map(|p| &p.song.player).collect();
+    let current = names
+        .iter()
+        .position(|name| Some(*name) == state.pinned.as_ref());
+    let next = current.map_or(1, |i| i + 1);
+    names
+        .get(next % names.len().max(1))
+        .map(|name| name.to_string())
+}
+
+fn earliest(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// 当前字每多唱一个字符所需的时间，用于逐字高亮的刷新
+fn karaoke_step(status: &LyricsStatus) -> Option<f64> {
+    let words = status.current_line.words.as_ref()?;
+    let word = words.get(status.current_word?)?;
+    let end = word.end.or(status.next_word_timestamp)?;
+    let chars = word.text.chars().count().max(1) as f64;
+    Some((end - word.timestamp) / chars)
+}
@@ -5,5 +5,6 @@ pub struct WaybarOutput {
     pub text: String,
     pub alt: String,
     pub tooltip: String,
-    pub class: String,
+    /// CSS 类名，Waybar 接受字符串数组
+    pub class: Vec<String>,
 }
@@ -1,3 +1,6 @@
 mod render;
 
-pub use render::{render_empty, render_lyrics, render_no_song, render_song_info};
+pub use render::{
+    print, render_combined, render_empty, render_error, render_karaoke, render_lyrics,
+    render_no_song, render_no_track, render_paused, render_song_info, render_stopped,
+};
@@ -1,60 +1,200 @@
-use shared::models::SongInfo;
+use shared::config::KaraokeConfig;
+use shared::models::{LyricsStatus, SongInfo};
 
 use crate::models::WaybarOutput;
 
 /// hidden
-pub fn render_no_song() {
+pub fn render_no_song() -> WaybarOutput {
     // No song playing
-    let output = WaybarOutput {
+    WaybarOutput {
         // text: "No song playing".to_string(),
         text: String::new(),
         alt: "".to_string(),
         tooltip: "".to_string(),
-        class: "no-song".to_string(),
-    };
-    println!("{}", serde_json::to_string(&output).unwrap());
+        class: vec!["no-song".to_string()],
+    }
+}
+
+/// hidden, with the reason in the tooltip
+pub fn render_error(error: &str) -> WaybarOutput {
+    WaybarOutput {
+        text: String::new(),
+        alt: "".to_string(),
+        tooltip: error.to_string(),
+        class: vec!["error".to_string()],
+    }
 }
 
 #[allow(unused)]
 /// empty but show
-pub fn render_just() {
+pub fn render_just() -> WaybarOutput {
     // Output nothing (hidden state)
-    let output = WaybarOutput {
+    WaybarOutput {
         text: "...".to_string(),
         alt: "".to_string(),
         tooltip: "".to_string(),
-        class: "has-song".to_string(),
-    };
-    println!("{}", serde_json::to_string(&output).unwrap());
+        class: vec!["has-song".to_string()],
+    }
 }
 /// hidden
-pub fn render_empty() {
+pub fn render_empty() -> WaybarOutput {
     // Output nothing (hidden state)
-    let output = WaybarOutput {
+    WaybarOutput {
         text: String::new(),
         alt: "".to_string(),
         tooltip: "".to_string(),
-        class: "hidden".to_string(),
-    };
-    println!("{}", serde_json::to_string(&output).unwrap());
+        class: vec!["hidden".to_string()],
+    }
 }
 
 /// Render song info
-pub fn render_song_info(song_info: &SongInfo) {
+pub fn render_song_info(song_info: &SongInfo) -> WaybarOutput {
     let parsed_text = format!("{} - {}", song_info.artist, song_info.title);
-    let output = WaybarOutput {
+    WaybarOutput {
         text: parsed_text.to_string(),
         alt: "".to_string(),
         tooltip: parsed_text.to_string(),
-        class: "has-song".to_string(),
-    };
-    println!("{}", serde_json::to_string(&output).unwrap());
+        class: vec!["has-song".to_string()],
+    }
+}
+
+/// Render a paused song; hidden once it has been paused for too long
+pub fn render_paused(song_info: &SongInfo, idle: bool) -> WaybarOutput {
+    if idle {
+        return WaybarOutput {
+            text: String::new(),
+            alt: "".to_string(),
+            tooltip: "".to_string(),
+            class: vec!["paused".to_string(), "idle".to_string()],
+        };
+    }
+    let mut output = render_song_info(song_info);
+    output.class.push("paused".to_string());
+    output
+}
+
+/// hidden, the player is stopped
+pub fn render_stopped() -> WaybarOutput {
+    WaybarOutput {
+        text: String::new(),
+        alt: "".to_string(),
+        tooltip: "".to_string(),
+        class: vec!["stopped".to_string()],
+    }
+}
+
+/// hidden, the player has no track loaded
+pub fn render_no_track() -> WaybarOutput {
+    WaybarOutput {
+        text: String::new(),
+        alt: "".to_string(),
+        tooltip: "".to_string(),
+        class: vec!["no-track".to_string()],
+    }
 }
 
 /// Render lyrics line
-pub fn render_lyrics(current_lyric_line: &str, next_lyric_line: String, tooltip: String) {
-    let output = get_lyrics_output(current_lyric_line, next_lyric_line, tooltip);
-    println!("{}", serde_json::to_string(&output).unwrap());
+pub fn render_lyrics(
+    current_lyric_line: &str,
+    next_lyric_line: String,
+    tooltip: String,
+) -> WaybarOutput {
+    get_lyrics_output(current_lyric_line, next_lyric_line, tooltip)
+}
+
+/// Render lyrics line with the sung part highlighted (Pango markup)
+pub fn render_karaoke(
+    status: &LyricsStatus,
+    next_lyric_line: String,
+    tooltip: String,
+    style: &KaraokeConfig,
+) -> WaybarOutput {
+    let mut output = get_lyrics_output(&status.current_line.text, next_lyric_line, tooltip);
+    if !status.current_line.text.is_empty() {
+        output.text = karaoke_markup(status, style);
+    }
+    output
+}
+
+/// 把多个播放器的输出拼成一个模块，空文本的播放器不占位置
+pub fn render_combined(outputs: Vec<WaybarOutput>, separator: &str) -> WaybarOutput {
+    let join = |field: fn(&WaybarOutput) -> &str, separator: &str| {
+        outputs
+            .iter()
+            .map(field)
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+            .join(separator)
+    };
+    let mut class: Vec<String> = Vec::new();
+    for output in &outputs {
+        for name in &output.class {
+            if !class.contains(name) {
+                class.push(name.clone());
+            }
+        }
+    }
+    WaybarOutput {
+        text: join(|o| &o.text, separator),
+        alt: join(|o| &o.alt, separator),
+        tooltip: join(|o| &o.tooltip, "\n"),
+        class,
+    }
+}
+
+/// 输出一行 JSON
+pub fn print(output: &WaybarOutput) {
+    println!("{}", serde_json::to_string(output).unwrap());
+}
+
+/// 按逐字进度把当前行拆成已唱/未唱两段
+fn karaoke_markup(status: &LyricsStatus, style: &KaraokeConfig) -> String {
+    let text = &status.current_line.text;
+    let sung_chars = match (&status.current_line.words, status.current_word) {
+        (Some(words), Some(index)) => {
+            let done: usize = words[..index].iter().map(|w| w.text.chars().count()).sum();
+            let current = words[index].text.chars().count() as f64;
+            done + (current * status.word_progress).round() as usize
+        }
+        _ => 0,
+    };
+    let split = text
+        .char_indices()
+        .nth(sung_chars)
+        .map_or(text.len(), |(i, _)| i);
+    let (sung, unsung) = text.split_at(split);
+
+    let mut markup = String::new();
+    if !sung.is_empty() {
+        markup.push_str(&format!(
+            "<span {}>{}</span>",
+            style.sung_span,
+            escape_markup(sung)
+        ));
+    }
+    if !unsung.is_empty() {
+        markup.push_str(&format!(
+            "<span {}>{}</span>",
+            style.unsung_span,
+            escape_markup(unsung)
+        ));
+    }
+    markup
+}
+
+fn escape_markup(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\'' => escaped.push_str("&apos;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 fn get_lyrics_output(
@@ -67,13 +207,108 @@ fn get_lyrics_output(
             text: "...".to_string(),
             alt: "".to_string(),
             tooltip,
-            class: "has-lyrics".to_string(),
+            class: vec!["has-lyrics".to_string()],
         };
     }
     WaybarOutput {
         text: current_lyric_line.to_owned(),
         alt: next_lyric_line,
         tooltip,
-        class: "has-lyrics".to_string(),
+        class: vec!["has-lyrics".to_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::models::{LyricLine, LyricWord};
+
+    fn style() -> KaraokeConfig {
+        KaraokeConfig {
+            enabled: true,
+            sung_span: "s".to_string(),
+            unsung_span: "u".to_string(),
+        }
+    }
+
+    /// 按给定的字拆分的当前行，第 `word` 个字唱到 `progress`
+    fn status(words: &[&str], word: Option<usize>, progress: f64) -> LyricsStatus {
+        let words: Vec<_> = words
+            .iter()
+            .enumerate()
+            .map(|(i, text)| LyricWord {
+                timestamp: i as f64,
+                end: None,
+                text: text.to_string(),
+            })
+            .collect();
+        LyricsStatus {
+            current_line: LyricLine {
+                text: words.iter().map(|w| w.text.as_str()).collect(),
+                words: Some(words),
+                ..Default::default()
+            },
+            next_line: String::new(),
+            next_timestamp: None,
+            current_word: word,
+            word_progress: progress,
+            next_word_timestamp: None,
+            line_progress: 0.0,
+        }
+    }
+
+    #[test]
+    fn markup_is_escaped() {
+        assert_eq!(
+            escape_markup(r#"Rock & <Roll> "it's""#),
+            "Rock &amp; &lt;Roll&gt; &quot;it&apos;s&quot;"
+        );
+        assert_eq!(escape_markup("你好"), "你好");
+    }
+
+    #[test]
+    fn split_within_a_word() {
+        let status = status(&["Hel", "lo ", "world"], Some(2), 0.4);
+        assert_eq!(
+            karaoke_markup(&status, &style()),
+            "<span s>Hello wo</span><span u>rld</span>"
+        );
+    }
+
+    #[test]
+    fn split_counts_characters_not_bytes() {
+        let status = status(&["你好", "世界"], Some(1), 0.5);
+        assert_eq!(
+            karaoke_markup(&status, &style()),
+            "<span s>你好世</span><span u>界</span>"
+        );
+    }
+
+    #[test]
+    fn before_first_word_and_after_last() {
+        let before = status(&["a", "b"], None, 0.0);
+        assert_eq!(karaoke_markup(&before, &style()), "<span u>ab</span>");
+        let after = status(&["a", "b"], Some(1), 1.0);
+        assert_eq!(karaoke_markup(&after, &style()), "<span s>ab</span>");
+    }
+
+    #[test]
+    fn sung_and_unsung_parts_are_escaped() {
+        let status = status(&["<a>", "&"], Some(0), 1.0);
+        assert_eq!(
+            karaoke_markup(&status, &style()),
+            "<span s>&lt;a&gt;</span><span u>&amp;</span>"
+        );
+    }
+
+    #[test]
+    fn empty_line_keeps_placeholder() {
+        let output = render_karaoke(
+            &status(&[], None, 0.0),
+            String::new(),
+            String::new(),
+            &style(),
+        );
+        assert_eq!(output.text, "...");
     }
 }